@@ -1,9 +1,6 @@
-use framework::{application::CloningStrategy::CloneIfNecessary, f, Application};
+use framework::{application::CloningStrategy::CloneIfNecessary, f, routes, Application};
 
-use crate::{
-	frontend,
-	http::{middleware::MIDDLEWARE, routes},
-};
+use crate::{frontend, http::middleware::MIDDLEWARE};
 
 #[must_use]
 pub fn booststrap() -> Application {
@@ -13,10 +10,22 @@ pub fn booststrap() -> Application {
 		.with_telemetry()
         // register the application's middleware map...
 		.middleware(&MIDDLEWARE)
-        // register the application's routes...
-		.routes(routes::handler)
+		.fallible_middleware(
+			"session",
+			f!(pavex_session::StartSession::handle),
+			f!(pavex_session::SessionError::to_response),
+		)
+		.fallible_middleware(
+			"csrf",
+			f!(pavex_session::VerifyCsrfToken::handle),
+			f!(pavex_session::CsrfError::to_response),
+		)
+        // register the application's routes, with the URL generator and middleware arguments built from them...
+		.routes(routes!(crate::http::routes::handler))
         // initialize the application's session...
 		.register(pavex_session::register)
+        // register what the middleware depend on...
+		.register(crate::http::middleware::register)
         // register the application's frontend layer
 		.register(frontend::register)
         // and register the session config as a singleton.
@@ -1,7 +1,8 @@
 use cargo_px_env::generated_pkg_manifest_path;
-use std::error::Error;
+use framework::router::UrlGenerator;
+use std::{error::Error, fs, path::Path};
 
-/// Generate the `sdk` crate using Pavex's CLI.
+/// Generate the `sdk` crate using Pavex's CLI, the frontend's named-route helpers, and the `routes.json` listing.
 fn main() -> Result<(), Box<dyn Error>> {
 	let generated_dir = generated_pkg_manifest_path()?
 		.parent()
@@ -10,5 +11,17 @@ fn main() -> Result<(), Box<dyn Error>> {
 
 	app::booststrap().build(generated_dir)?;
 
+	let router = app::http::routes::handler();
+
+	fs::write(
+		Path::new(env!("CARGO_MANIFEST_DIR")).join("../frontend/src/routes.ts"),
+		UrlGenerator::new(&router).to_typescript(),
+	)?;
+
+	fs::write(
+		Path::new(env!("CARGO_MANIFEST_DIR")).join("../routes.json"),
+		router.to_json()?,
+	)?;
+
 	Ok(())
 }
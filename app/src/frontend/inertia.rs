@@ -16,7 +16,7 @@ use serde_json::json;
 use sha256::digest as sha256;
 use std::{future::IntoFuture, sync::Arc};
 
-use crate::frontend::vite::Vite;
+use crate::{frontend::vite::Vite, http::middleware::auth::AUTH_USER};
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Page<T = serde_json::Value> {
@@ -102,8 +102,7 @@ impl Inertia {
 }
 
 /// Middleware that handles Inertia requests.
-#[allow(clippy::future_not_send)]
-pub async fn middleware<C: IntoFuture<Output = Response>>(
+pub async fn middleware<C: IntoFuture<Output = Response, IntoFuture: Send> + Send>(
 	inertia: &Inertia,
 	request: &RequestHead,
 	next: Next<C>,
@@ -177,10 +176,18 @@ impl InertiaResponse {
 		props.insert(
 			"auth".to_string(),
 			json!({
-				"user": self.request.session.get::<i64>("auth.user")
+				"user": self.request.session.get_typed(&AUTH_USER)
 			}),
 		);
 		props.insert("flash".to_string(), json!(self.request.session.flashed()));
+		props.insert(
+			"errors".to_string(),
+			json!(self.request.session.errors().first_messages()),
+		);
+		props.insert(
+			"csrf_token".to_string(),
+			json!(self.request.session.masked_token()),
+		);
 
 		page
 	}
@@ -193,13 +200,14 @@ impl InertiaResponse {
                     <title>PingCRM</title>
                     <meta charset="UTF-8" />
                     <meta name="viewport" content="width=device-width, initial-scale=1.0" />
+                    <meta name="csrf-token" content="{}" />
                     {}{}
                 </head>
                 <body>
                     <div id="app" data-page='{}'></div>
                 </body>
             </html>
-        "#, self.vite.dev_scripts().unwrap_or_default(), self.vite.asset("src/index.tsx").unwrap(), serde_json::to_string(&self.get_page()).unwrap()}
+        "#, self.request.session.masked_token().unwrap_or_default(), self.vite.dev_scripts().unwrap_or_default(), self.vite.asset("src/index.tsx").unwrap(), serde_json::to_string(&self.get_page()).unwrap()}
 	}
 }
 
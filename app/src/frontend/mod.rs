@@ -41,6 +41,7 @@ pub fn serve_assets<E: RustEmbed>(
 }
 
 /// Register all required constructors and routes for Vite and Inertia.
+/// The Inertia middleware is applied per route, through the `inertia` middleware.
 /// Note that the type parameter `E` should be a fully qualified import path.
 pub fn register(bp: &mut Blueprint) {
 	// Vite
@@ -65,5 +66,4 @@ pub fn register(bp: &mut Blueprint) {
 		f!(crate::frontend::inertia::Inertia::new),
 		Lifecycle::RequestScoped,
 	);
-	bp.wrap(f!(crate::frontend::inertia::middleware));
 }
@@ -1,19 +1,35 @@
 use std::future::IntoFuture;
 
-use framework::http::{middleware::Next, response::Response, Redirect};
-use pavex_session::Session;
+use ensemble::Model;
+use framework::{
+	http::{
+		header,
+		middleware::Next,
+		request::RequestHead,
+		response::{IntoResponse, Response},
+		Redirect,
+	},
+	router::UrlGenerator,
+};
+use pavex_session::{Session, SessionKey};
+use sha256::digest as sha256;
+
+use crate::models::PersonalAccessToken;
+
+/// The ID of the logged-in user.
+pub const AUTH_USER: SessionKey<u64> = SessionKey::new("auth.user");
 
 pub struct EnsureLoggedIn {}
 
 impl EnsureLoggedIn {
-	pub async fn handle<C: IntoFuture<Output = Response>>(
+	pub async fn handle<C: IntoFuture<Output = Response, IntoFuture: Send> + Send>(
 		session: Session,
+		urls: &UrlGenerator,
 		next: Next<C>,
 	) -> Response {
-		dbg!("called auth middleware");
-
-		if session.get::<u64>("auth.user").is_none() {
-			return Redirect::to("/auth/login");
+		if session.get_typed(&AUTH_USER).is_none() {
+			return Redirect::route(urls, "auth.login.index", &[])
+				.unwrap_or_else(IntoResponse::into_response);
 		}
 
 		next.into_future().await
@@ -23,15 +39,87 @@ impl EnsureLoggedIn {
 pub struct RedirectToDashboard {}
 
 impl RedirectToDashboard {
-	pub async fn handle<C: IntoFuture<Output = Response>>(
+	pub async fn handle<C: IntoFuture<Output = Response, IntoFuture: Send> + Send>(
 		session: Session,
+		urls: &UrlGenerator,
 		next: Next<C>,
 	) -> Response {
-		dbg!("called guest middleware", &session);
-		if session.get::<u64>("auth.user").is_some() {
-			return Redirect::to("/dashboard");
+		if session.get_typed(&AUTH_USER).is_some() {
+			return Redirect::route(urls, "dashboard", &[])
+				.unwrap_or_else(IntoResponse::into_response);
 		}
 
 		next.into_future().await
 	}
 }
+
+/// The user a bearer token authenticated, for API routes.
+#[derive(Debug, Clone)]
+pub struct ApiUser {
+	pub id: u64,
+}
+
+impl ApiUser {
+	/// Look up the personal access token sent as `Authorization: Bearer <token>`.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the token is missing or unknown, or the lookup fails.
+	pub async fn extract(req: &RequestHead) -> Result<Self, TokenError> {
+		let token = req
+			.headers
+			.get(header::AUTHORIZATION)
+			.and_then(|value| value.to_str().ok())
+			.and_then(|value| value.strip_prefix("Bearer "))
+			.ok_or(TokenError::Missing)?;
+
+		let token = PersonalAccessToken::query()
+			.r#where("token", '=', sha256(token.trim()))
+			.first::<PersonalAccessToken>()
+			.await?
+			.ok_or(TokenError::Invalid)?;
+
+		Ok(Self { id: token.user_id })
+	}
+}
+
+/// Reject requests without a valid personal access token.
+pub struct AuthenticateToken {}
+
+impl AuthenticateToken {
+	pub async fn handle<C: IntoFuture<Output = Response, IntoFuture: Send> + Send>(
+		_user: &ApiUser,
+		next: Next<C>,
+	) -> Response {
+		next.into_future().await
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TokenError {
+	#[error("Missing bearer token.")]
+	Missing,
+
+	#[error("Invalid bearer token.")]
+	Invalid,
+
+	#[error("failed to look up the access token: {0}")]
+	Database(#[from] ensemble::Error),
+}
+
+impl TokenError {
+	/// Build the response sent when a request can't be authenticated with a token.
+	/// This is the error handler registered for [`ApiUser::extract`].
+	pub fn to_response(&self) -> Response {
+		match self {
+			Self::Missing | Self::Invalid => {
+				Response::unauthorized().set_typed_body(self.to_string())
+			},
+			Self::Database(e) => {
+				tracing::error!(error.msg = %e, error.details = ?e, "Failed to look up an access token");
+
+				Response::internal_server_error()
+			},
+		}
+	}
+}
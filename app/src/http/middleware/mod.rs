@@ -1,9 +1,60 @@
-use pavex::{blueprint::reflection::RawCallable, f};
+use framework::router::{Router, SerializedRoute};
+use pavex::{
+	blueprint::{
+		constructor::{CloningStrategy, Lifecycle},
+		reflection::RawCallable,
+		Blueprint,
+	},
+	f,
+	request::{path::MatchedPathPattern, RequestHead},
+};
+use pavex_session::RouteName;
 use phf::phf_map;
 
 pub mod auth;
+pub mod throttle;
 
 pub const MIDDLEWARE: phf::Map<&str, RawCallable> = phf_map! {
 	"auth" => f!(crate::http::middleware::auth::EnsureLoggedIn::handle),
 	"guest" => f!(crate::http::middleware::auth::RedirectToDashboard::handle),
+	"inertia" => f!(crate::frontend::inertia::middleware),
+	"token" => f!(crate::http::middleware::auth::AuthenticateToken::handle),
+	"throttle" => f!(crate::http::middleware::throttle::ThrottleRequests::handle),
+};
+
+/// Groups of middleware routes can refer to by a single name.
+/// `session` and `csrf` are fallible, so they're registered with their error handlers in `booststrap`.
+pub const MIDDLEWARE_GROUPS: phf::Map<&str, &[&str]> = phf_map! {
+	"web" => &["session", "csrf", "inertia"],
+	"api" => &["token", "throttle:60,1"],
 };
+
+/// Register the constructors the middleware above depend on.
+pub fn register(bp: &mut Blueprint) {
+	bp.constructor(
+		f!(crate::http::middleware::auth::ApiUser::extract),
+		Lifecycle::RequestScoped,
+	)
+	.error_handler(f!(crate::http::middleware::auth::TokenError::to_response));
+
+	bp.constructor(
+		f!(crate::http::middleware::throttle::RateLimiter::new),
+		Lifecycle::Singleton,
+	)
+	.cloning(CloningStrategy::CloneIfNecessary);
+
+	bp.constructor(
+		f!(crate::http::middleware::route_name),
+		Lifecycle::RequestScoped,
+	);
+}
+
+/// The name of the matched route, so `session.csrf.exclude_routes` can refer to it.
+pub fn route_name(router: &Router, req: &RequestHead, route: &MatchedPathPattern) -> RouteName {
+	RouteName(
+		router
+			.route(&req.method, route.inner())
+			.and_then(SerializedRoute::name)
+			.map(ToString::to_string),
+	)
+}
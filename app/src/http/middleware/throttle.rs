@@ -0,0 +1,162 @@
+use std::{
+	collections::HashMap,
+	future::IntoFuture,
+	sync::{Arc, Mutex, PoisonError},
+	time::{Duration, Instant},
+};
+
+use framework::{
+	http::{
+		header,
+		middleware::Next,
+		request::{path::MatchedPathPattern, RequestHead},
+		response::Response,
+		HeaderName, HeaderValue, StatusCode,
+	},
+	router::RouteMiddleware,
+};
+use sha256::digest as sha256;
+
+/// Rate limit a route, configured as `throttle:max_attempts,decay_minutes` (`throttle:60,1` by default).
+///
+/// Requests are counted per route and per `Authorization` header, so list it after the middleware that
+/// authenticates the request, like the `api` group does. The client address isn't available to middleware,
+/// so a request without credentials can't be told apart from any other: it is answered with a
+/// `500 Internal Server Error` instead of sharing a single limit with every anonymous client.
+pub struct ThrottleRequests {}
+
+impl ThrottleRequests {
+	pub async fn handle<C: IntoFuture<Output = Response, IntoFuture: Send> + Send>(
+		req: &RequestHead,
+		route: &MatchedPathPattern,
+		middleware: &RouteMiddleware,
+		limiter: &RateLimiter,
+		next: Next<C>,
+	) -> Response {
+		let arguments = middleware
+			.arguments(&req.method, route.inner(), "throttle")
+			.unwrap_or_default();
+
+		let Some(limit) = Limit::parse(arguments) else {
+			tracing::error!(
+				route = route.inner(),
+				arguments = ?arguments,
+				"Invalid throttle arguments, expected `throttle:max_attempts,decay_minutes`"
+			);
+
+			return Response::internal_server_error();
+		};
+
+		let Some(client) = req
+			.headers
+			.get(header::AUTHORIZATION)
+			.and_then(|value| value.to_str().ok())
+			.map(sha256)
+		else {
+			tracing::error!(
+				route = route.inner(),
+				"Throttled request without credentials, list `throttle` after the middleware authenticating the route"
+			);
+
+			return Response::internal_server_error();
+		};
+
+		match limiter.hit(format!("{} {}|{client}", req.method, route.inner()), &limit) {
+			Ok(remaining) => {
+				let mut response = next.into_future().await;
+
+				let headers = response.headers_mut();
+				headers.insert(
+					HeaderName::from_static("x-ratelimit-limit"),
+					HeaderValue::from(limit.max_attempts),
+				);
+				headers.insert(
+					HeaderName::from_static("x-ratelimit-remaining"),
+					HeaderValue::from(remaining),
+				);
+
+				response
+			},
+			Err(retry_after) => Response::new(StatusCode::TOO_MANY_REQUESTS)
+				.insert_header(
+					header::RETRY_AFTER,
+					HeaderValue::from(
+						retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0),
+					),
+				)
+				.set_typed_body("Too Many Attempts."),
+		}
+	}
+}
+
+struct Limit {
+	max_attempts: u32,
+	decay: Duration,
+}
+
+impl Limit {
+	fn parse(arguments: &[String]) -> Option<Self> {
+		let (max_attempts, decay_minutes): (u32, u64) = match arguments {
+			[] => (60, 1),
+			[max_attempts] => (max_attempts.parse().ok()?, 1),
+			[max_attempts, decay_minutes] => {
+				(max_attempts.parse().ok()?, decay_minutes.parse().ok()?)
+			},
+			_ => return None,
+		};
+
+		Some(Self {
+			max_attempts,
+			decay: Duration::from_secs(decay_minutes.saturating_mul(60)),
+		})
+	}
+}
+
+/// Counts the requests of each throttled client in fixed windows, in memory.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+	windows: Arc<Mutex<HashMap<String, Window>>>,
+}
+
+#[derive(Debug)]
+struct Window {
+	hits: u32,
+	resets_at: Instant,
+}
+
+impl RateLimiter {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Count a request, returning how many are left in the current window, or how long until it resets
+	/// once the limit is reached. Expired windows are dropped whenever a new one opens.
+	fn hit(&self, key: String, limit: &Limit) -> Result<u32, Duration> {
+		let now = Instant::now();
+		let mut windows = self.windows.lock().unwrap_or_else(PoisonError::into_inner);
+
+		if !windows
+			.get(&key)
+			.is_some_and(|window| window.resets_at > now)
+		{
+			windows.retain(|_, window| window.resets_at > now);
+			windows.insert(
+				key.clone(),
+				Window {
+					hits: 0,
+					resets_at: now + limit.decay,
+				},
+			);
+		}
+
+		let window = windows.get_mut(&key).unwrap_or_else(|| unreachable!());
+		if window.hits >= limit.max_attempts {
+			return Err(window.resets_at - now);
+		}
+
+		window.hits += 1;
+
+		Ok(limit.max_attempts - window.hits)
+	}
+}
@@ -0,0 +1,13 @@
+use framework::http::response::{body::Json, Response};
+use serde_json::json;
+
+use crate::http::middleware::auth::ApiUser;
+
+/// The user the request's bearer token belongs to.
+///
+/// # Panics
+///
+/// Panics if the body can't be serialized, which can't happen for a JSON object.
+pub fn user(user: &ApiUser) -> Response {
+	Response::ok().set_typed_body(Json::new(json!({ "id": user.id })).unwrap())
+}
@@ -1,20 +1,24 @@
 use ensemble::{types::Hashed, Model};
-use framework::http::{
-	request::body::JsonBody,
-	response::{IntoResponse, Response},
-	Redirect, StatusCode,
+use framework::{
+	http::{
+		request::body::JsonBody,
+		response::{IntoResponse, Response},
+		Redirect, StatusCode,
+	},
+	router::UrlGenerator,
 };
-use pavex_session::Session;
+use pavex_session::{ErrorBag, Session};
 use serde_json::json;
 
 use crate::{
 	frontend::{inertia::InertiaResponse, Inertia},
+	http::middleware::auth::AUTH_USER,
 	models::User,
 };
 
 pub struct AuthenticatedSessionController;
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct LoginRequest {
 	email: String,
 	password: Hashed<String>,
@@ -31,10 +35,11 @@ impl AuthenticatedSessionController {
 	///
 	/// This function will panic if the database query fails.
 	pub async fn store(
-		inertia: &Inertia,
 		mut session: Session,
+		urls: &UrlGenerator,
 		JsonBody(req): JsonBody<LoginRequest>,
 	) -> Response {
+		let email = req.email.clone();
 		let user = User::query()
 			.r#where("email", '=', req.email)
 			.r#where("password", '=', req.password)
@@ -43,24 +48,24 @@ impl AuthenticatedSessionController {
 			.unwrap();
 
 		let Some(user) = user else {
-			session.flash(
-				"errors",
-				json!({
-					"email": ["Invalid email or password"]
-				}),
-			);
+			// Send the user back to the form, which picks the input and errors up from the session.
+			session.with_input(json!({ "email": email }));
+			session.with_errors(ErrorBag::new().with("email", "Invalid email or password"));
 
-			return inertia.render("Auth/Login", ()).into_response();
+			return Redirect::route(urls, "auth.login.index", &[])
+				.unwrap_or_else(IntoResponse::into_response);
 		};
 
-		session.set("auth.user", user.id);
+		session.regenerate(true);
+		session.set_typed(&AUTH_USER, user.id);
 
 		StatusCode::OK.into_response()
 	}
 
-	pub fn destroy(mut session: Session) -> Response {
-		session.forget("auth.user");
+	pub fn destroy(mut session: Session, urls: &UrlGenerator) -> Response {
+		session.invalidate();
+		session.regenerate_token();
 
-		Redirect::to("/auth/login")
+		Redirect::route(urls, "auth.login.index", &[]).unwrap_or_else(IntoResponse::into_response)
 	}
 }
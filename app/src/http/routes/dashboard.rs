@@ -0,0 +1,6 @@
+use crate::frontend::{inertia::InertiaResponse, Inertia};
+
+/// The page users land on once they're logged in.
+pub fn index(inertia: &Inertia) -> InertiaResponse {
+	inertia.render("Dashboard", ())
+}
@@ -2,33 +2,57 @@
 
 use framework::{f, router::Router};
 
+use crate::http::middleware::MIDDLEWARE_GROUPS;
+
+pub mod api;
 pub mod auth;
+pub mod dashboard;
 pub mod system;
 
 pub fn handler() -> Router {
 	let mut router = Router::new();
+	router.middleware_groups(&MIDDLEWARE_GROUPS);
 
 	router
 		.name("health-check")
 		.get("/healthz", f!(crate::http::routes::system::health_check));
 
-	router.name("auth").prefix("/auth").group(|mut router| {
-		router
-			.resource(
-				"/login",
-				f!(crate::http::routes::auth::AuthenticatedSessionController),
-			)
-			.only(vec!["index", "store"])
-			.middleware("guest");
-
-		router
-			.delete(
-				"/logout",
-				f!(crate::http::routes::auth::AuthenticatedSessionController::destroy),
-			)
-			.name(".logout")
-			.middleware("auth");
-	});
+	router
+		.name("auth.")
+		.prefix("/auth")
+		.middleware("web")
+		.group(|mut router| {
+			router
+				.resource(
+					"/login",
+					f!(crate::http::routes::auth::AuthenticatedSessionController),
+				)
+				.only(vec!["index", "store"])
+				.middleware("guest");
+
+			router
+				.delete(
+					"/logout",
+					f!(crate::http::routes::auth::AuthenticatedSessionController::destroy),
+				)
+				.name("logout")
+				.middleware("auth");
+		});
+
+	router
+		.name("dashboard")
+		.middleware(vec!["web", "auth"])
+		.get("/dashboard", f!(crate::http::routes::dashboard::index));
+
+	router
+		.name("api.")
+		.prefix("/api")
+		.middleware("api")
+		.group(|mut router| {
+			router
+				.get("/user", f!(crate::http::routes::api::user))
+				.name("user");
+		});
 
 	router
 }
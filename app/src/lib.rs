@@ -1,5 +1,4 @@
 #![warn(clippy::all, clippy::pedantic, clippy::nursery)]
-#![allow(clippy::future_not_send)]
 
 #[derive(RustEmbed)]
 #[folder = "../frontend/dist/"]
@@ -13,3 +13,16 @@ pub struct User {
 	pub created_at: DateTime,
 	pub updated_at: DateTime,
 }
+
+/// A token authenticating API requests on behalf of a user, sent as `Authorization: Bearer <token>`.
+#[derive(Debug, Model)]
+pub struct PersonalAccessToken {
+	pub id: u64,
+	pub user_id: u64,
+	pub name: String,
+	/// The SHA-256 digest of the token, so the table never holds usable tokens.
+	pub token: String,
+
+	pub created_at: DateTime,
+	pub updated_at: DateTime,
+}
@@ -3,13 +3,13 @@ use std::collections::HashMap;
 use pavex::{
 	blueprint::{
 		constructor::Lifecycle,
-		internals::RegisteredCallable,
-		reflection::{RawCallable, RawCallableIdentifiers},
+		internals::{RegisteredCallable, RegisteredWrappingMiddleware},
+		reflection::{Location, RawCallable, RawCallableIdentifiers},
 		Blueprint,
 	},
 	f,
 	request::{
-		body::{BodySizeLimit, BufferedBody, JsonBody},
+		body::{BodySizeLimit, JsonBody},
 		path::PathParams,
 		query::QueryParams,
 	},
@@ -18,16 +18,22 @@ use pavex::{
 pub use pavex::blueprint::constructor::CloningStrategy;
 
 use self::CallbackType::{GlobalMiddleware, RegisterRoutes};
-use crate::router::Router;
+use crate::router::Routes;
 
 #[derive(Default)]
 pub struct Application {
 	blueprint: Blueprint,
 	callbacks: Vec<Callback>,
-	middleware: HashMap<String, RegisteredCallable>,
+	middleware: HashMap<String, RegisteredWrappingMiddleware>,
 }
 
 impl Application {
+	/// Create an application with Pavex's default extractors registered.
+	///
+	/// Unlike Pavex's own `BufferedBody` constructor, the one registered here hands `GET`, `HEAD` and
+	/// `OPTIONS` requests an empty body without reading the incoming one, so `BufferedBody` and the
+	/// extractors built on it (like `JsonBody`) see no payload on those methods, and their size limit
+	/// isn't enforced. Handlers that need the body of a safe request must read `RawIncomingBody` instead.
 	pub fn new() -> Self {
 		Self::default().default_extractors()
 	}
@@ -96,9 +102,12 @@ impl Application {
 		for (name, callable) in middleware.into_iter() {
 			self.middleware.insert(
 				name.to_string(),
-				RegisteredCallable {
-					location: std::panic::Location::caller().into(),
-					callable: RawCallableIdentifiers::from_raw_callable(callable.clone()),
+				RegisteredWrappingMiddleware {
+					middleware: RegisteredCallable {
+						location: std::panic::Location::caller().into(),
+						callable: RawCallableIdentifiers::from_raw_callable(callable.clone()),
+					},
+					error_handler: None,
 				},
 			);
 		}
@@ -106,6 +115,33 @@ impl Application {
 		self
 	}
 
+	/// Register a named middleware that returns a `Result`, along with the handler for its error.
+	#[track_caller]
+	pub fn fallible_middleware(
+		mut self,
+		name: &str,
+		middleware: RawCallable,
+		error_handler: RawCallable,
+	) -> Self {
+		let location: Location = std::panic::Location::caller().into();
+
+		self.middleware.insert(
+			name.to_string(),
+			RegisteredWrappingMiddleware {
+				middleware: RegisteredCallable {
+					location: location.clone(),
+					callable: RawCallableIdentifiers::from_raw_callable(middleware),
+				},
+				error_handler: Some(RegisteredCallable {
+					location,
+					callable: RawCallableIdentifiers::from_raw_callable(error_handler),
+				}),
+			},
+		);
+
+		self
+	}
+
 	pub fn with_telemetry(self) -> Self {
 		self.request_scoped(
 			f!(crate::telemetry::RootSpan::new),
@@ -121,14 +157,28 @@ impl Application {
 		self
 	}
 
-	pub fn routes(mut self, routes: impl FnOnce() -> Router) -> Self {
-		let router = routes();
+	/// Register the routes built by the given function, named with [`routes!`](crate::routes).
+	///
+	/// The same function provides the `Router` singleton, which the `RouteMiddleware` and `UrlGenerator`
+	/// singletons are built from, so they always match the routes and middleware groups registered here.
+	pub fn routes(mut self, routes: Routes) -> Self {
+		let router = (routes.build)();
 
 		self.callbacks.push(Callback::new(RegisterRoutes, |app| {
 			router.register(&mut app.blueprint, &app.middleware);
 		}));
 
-		self
+		self.singleton(routes.callable, CloningStrategy::CloneIfNecessary, None)
+			.singleton(
+				f!(crate::router::RouteMiddleware::new),
+				CloningStrategy::CloneIfNecessary,
+				None,
+			)
+			.singleton(
+				f!(crate::router::UrlGenerator::new),
+				CloningStrategy::CloneIfNecessary,
+				None,
+			)
 	}
 
 	#[cfg(feature = "build")]
@@ -157,9 +207,15 @@ impl Application {
 		JsonBody::register(&mut self.blueprint);
 		PathParams::register(&mut self.blueprint);
 		QueryParams::register(&mut self.blueprint);
-		BufferedBody::register(&mut self.blueprint);
 		BodySizeLimit::register(&mut self.blueprint);
 
+		// Registered by hand instead of `BufferedBody::register`, so safe requests skip reading the body.
+		self.blueprint
+			.constructor(f!(crate::http::buffer_body), Lifecycle::RequestScoped)
+			.error_handler(f!(
+				pavex::request::body::errors::ExtractBufferedBodyError::into_response
+			));
+
 		self
 	}
 }
@@ -1,12 +1,62 @@
-use pavex::response::Response;
+use pavex::{
+	request::{
+		body::{errors::ExtractBufferedBodyError, BodySizeLimit, BufferedBody, RawIncomingBody},
+		RequestHead,
+	},
+	response::Response,
+};
+
+use crate::router::{UrlError, UrlGenerator};
 
 pub use pavex::{http::*, middleware, request, response};
 
 pub struct Redirect {}
 
 impl Redirect {
+	/// Redirect to the given URL with a `303 See Other`, which is always followed with a `GET`, so it can
+	/// answer form submissions and `PUT`, `PATCH` or `DELETE` requests alike.
 	pub fn to(url: &str) -> Response {
-		Response::temporary_redirect()
+		Response::new(StatusCode::SEE_OTHER)
 			.insert_header(header::LOCATION, HeaderValue::from_str(url).unwrap())
 	}
+
+	/// Redirect to the route with the given name.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the URL for the route can't be generated.
+	pub fn route(
+		urls: &UrlGenerator,
+		name: &str,
+		params: &[(&str, &str)],
+	) -> Result<Response, UrlError> {
+		Ok(Self::to(&urls.route(name, params)?))
+	}
+}
+
+/// Buffer the request body for the handlers and middleware that read it.
+///
+/// `GET`, `HEAD` and `OPTIONS` requests are handed an empty body without reading the incoming one,
+/// so middleware that only inspects the body of state-changing requests (like CSRF verification)
+/// doesn't make every page visit wait for it.
+///
+/// # Errors
+///
+/// Returns an error if the body can't be read or exceeds the configured size limit.
+pub async fn buffer_body(
+	request_head: &RequestHead,
+	body: RawIncomingBody,
+	body_size_limit: BodySizeLimit,
+) -> Result<BufferedBody, ExtractBufferedBodyError> {
+	let is_reading = request_head.method == Method::GET
+		|| request_head.method == Method::HEAD
+		|| request_head.method == Method::OPTIONS;
+
+	if is_reading {
+		return Ok(BufferedBody {
+			bytes: Default::default(),
+		});
+	}
+
+	BufferedBody::extract(request_head, body, body_size_limit).await
 }
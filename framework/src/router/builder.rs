@@ -8,6 +8,7 @@ use pavex::{
 
 use super::{
 	group::{GroupRouter, RouteGroup},
+	join_paths,
 	route::{Route, SerializedRoute},
 	Router,
 };
@@ -53,6 +54,8 @@ impl<'r> Builder<'r> {
 			group.prefix(path);
 		}
 
+		group.group.middleware = self.middleware.clone();
+
 		group
 	}
 }
@@ -68,10 +71,7 @@ macro_rules! impl_method {
 						name: self.name.clone(),
 						method: Method::$method,
 						middleware: self.middleware.clone(),
-						path: self
-							.path
-							.as_ref()
-							.map_or_else(|| path.to_string(), |prefix| format!("{prefix}{path}")),
+						path: join_paths(self.path.as_deref().unwrap_or_default(), path),
 						request_handler: RegisteredCallable {
 							location: std::panic::Location::caller().into(),
 							callable: RawCallableIdentifiers::from_raw_callable(callable),
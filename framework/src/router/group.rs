@@ -1,6 +1,6 @@
 use std::ops::{Deref, DerefMut};
 
-use super::{builder::OneOrMultiple, route::SerializedRoute, Router};
+use super::{builder::OneOrMultiple, join_paths, middleware, route::SerializedRoute, Router};
 
 pub struct RouteGroup<'r> {
 	pub(crate) router: &'r mut Router,
@@ -29,7 +29,7 @@ impl<'r> Drop for RouteGroup<'r> {
 	fn drop(&mut self) {
 		for mut route in self.group.routes.drain(..) {
 			if let Some(prefix) = &self.group.prefix {
-				route.path = format!("{prefix}{}", route.path);
+				route.path = join_paths(prefix, &route.path);
 			}
 
 			if let Some(name) = &self.group.name {
@@ -40,6 +40,40 @@ impl<'r> Drop for RouteGroup<'r> {
 				}
 			}
 
+			// Outer groups run first, so their middleware goes before the route's own.
+			// Middleware listed again further in keeps its outermost position, but the innermost arguments.
+			let inherited = self
+				.group
+				.middleware
+				.iter()
+				.filter(|reference| {
+					let name = middleware::name(reference);
+
+					!route
+						.excluded_middleware
+						.iter()
+						.any(|excluded| excluded == name)
+				})
+				.map(|reference| {
+					route
+						.middleware
+						.iter()
+						.find(|own| middleware::name(own) == middleware::name(reference))
+						.unwrap_or(reference)
+						.clone()
+				})
+				.collect::<Vec<_>>();
+			let own = route
+				.middleware
+				.into_iter()
+				.filter(|reference| {
+					!inherited
+						.iter()
+						.any(|other| middleware::name(other) == middleware::name(reference))
+				})
+				.collect::<Vec<_>>();
+			route.middleware = inherited.into_iter().chain(own).collect();
+
 			self.router.store_route(route);
 		}
 	}
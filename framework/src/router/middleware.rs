@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use pavex::http::Method;
+
+use super::Router;
+
+/// Named lists of middleware, expanded wherever the group's name is used on a route.
+pub(crate) type MiddlewareGroups = HashMap<String, Vec<String>>;
+
+/// A middleware as referenced on a route, e.g. `auth` or `throttle:60,1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MiddlewareReference {
+	pub name: String,
+	pub arguments: Vec<String>,
+}
+
+impl MiddlewareReference {
+	/// Split a reference into the middleware name and its comma-separated arguments.
+	pub fn parse(reference: &str) -> Self {
+		let (name, arguments) = reference.split_once(':').unwrap_or((reference, ""));
+
+		Self {
+			name: name.trim().to_string(),
+			arguments: arguments
+				.split(',')
+				.map(str::trim)
+				.filter(|argument| !argument.is_empty())
+				.map(ToString::to_string)
+				.collect(),
+		}
+	}
+}
+
+/// The middleware each route runs, with their arguments, so parameterized middleware can read them
+/// at runtime.
+#[derive(Debug, Clone, Default)]
+pub struct RouteMiddleware {
+	routes: HashMap<(Method, String), Vec<MiddlewareReference>>,
+}
+
+impl RouteMiddleware {
+	/// Resolve the middleware of every route, expanding the router's middleware groups.
+	pub fn new(router: &Router) -> Self {
+		let groups = &router.middleware_groups;
+
+		Self {
+			routes: router
+				.routes()
+				.iter()
+				.map(|route| {
+					(
+						(route.method.clone(), route.path.clone()),
+						resolve(&route.middleware, &route.excluded_middleware, groups),
+					)
+				})
+				.collect(),
+		}
+	}
+
+	/// The arguments the route matching `method` and `path` passes to the middleware with the given
+	/// name, or `None` if the route doesn't run it.
+	///
+	/// `path` is the route's pattern, as exposed by `MatchedPathPattern`.
+	pub fn arguments(&self, method: &Method, path: &str, middleware: &str) -> Option<&[String]> {
+		self.routes
+			.get(&(method.clone(), path.to_string()))?
+			.iter()
+			.find(|reference| reference.name == middleware)
+			.map(|reference| reference.arguments.as_slice())
+	}
+}
+
+pub(crate) fn collect_groups<
+	'a,
+	I: IntoIterator<Item = (&'a &'static str, &'a &'static [&'static str])>,
+>(
+	groups: I,
+) -> MiddlewareGroups {
+	groups
+		.into_iter()
+		.map(|(name, middleware)| {
+			(
+				name.to_string(),
+				middleware.iter().map(ToString::to_string).collect(),
+			)
+		})
+		.collect()
+}
+
+/// Expand the middleware groups in a route's middleware list and parse the resulting references.
+///
+/// Groups may contain other groups. A middleware is only added the first time its name appears, and
+/// middleware the route opted out of are skipped, even when they come from a group. Arguments listed
+/// on the route itself win over the ones a group passes to the same middleware.
+pub(crate) fn resolve(
+	middleware: &[String],
+	excluded: &[String],
+	groups: &MiddlewareGroups,
+) -> Vec<MiddlewareReference> {
+	let mut resolved = Vec::new();
+	expand(middleware, excluded, groups, &mut Vec::new(), &mut resolved);
+
+	let explicit = middleware
+		.iter()
+		.map(|reference| MiddlewareReference::parse(reference))
+		.filter(|reference| !groups.contains_key(&reference.name))
+		.collect::<Vec<_>>();
+
+	for reference in &mut resolved {
+		if let Some(explicit) = explicit.iter().find(|other| other.name == reference.name) {
+			reference.arguments.clone_from(&explicit.arguments);
+		}
+	}
+
+	resolved
+}
+
+fn expand(
+	middleware: &[String],
+	excluded: &[String],
+	groups: &MiddlewareGroups,
+	expanding: &mut Vec<String>,
+	resolved: &mut Vec<MiddlewareReference>,
+) {
+	for reference in middleware {
+		let reference = MiddlewareReference::parse(reference);
+
+		if excluded.contains(&reference.name)
+			|| resolved.iter().any(|other| other.name == reference.name)
+		{
+			continue;
+		}
+
+		let Some(group) = groups.get(&reference.name) else {
+			resolved.push(reference);
+			continue;
+		};
+
+		assert!(
+			!expanding.contains(&reference.name),
+			"Middleware group `{}` includes itself (via {}).",
+			reference.name,
+			expanding.join(" -> ")
+		);
+
+		expanding.push(reference.name);
+		expand(group, excluded, groups, expanding, resolved);
+		expanding.pop();
+	}
+}
+
+/// The name of a middleware reference, without its arguments.
+pub(crate) fn name(reference: &str) -> &str {
+	reference
+		.split_once(':')
+		.map_or(reference, |(name, _)| name)
+		.trim()
+}
@@ -1,9 +1,12 @@
-use std::collections::HashMap;
+use std::{
+	collections::{hash_map::Entry, HashMap},
+	fmt::Display,
+};
 
 use pavex::{
 	blueprint::{
 		internals::{RegisteredCallable, RegisteredRoute, RegisteredWrappingMiddleware},
-		reflection::{RawCallable, RawCallableIdentifiers},
+		reflection::{Location, RawCallable, RawCallableIdentifiers},
 		Blueprint,
 	},
 	http::Method,
@@ -12,18 +15,46 @@ use pavex::{
 use self::{
 	builder::{Builder, OneOrMultiple},
 	group::{GroupRouter, RouteGroup, SerializedRouteGroup},
+	middleware::{collect_groups, MiddlewareGroups},
 	resource::Resource,
-	route::{Route, SerializedRoute},
+	route::Route,
 };
 
+pub use middleware::{MiddlewareReference, RouteMiddleware};
+pub use route::SerializedRoute;
+pub use url::{UrlError, UrlGenerator};
+
 mod builder;
 mod group;
+pub(crate) mod middleware;
 mod resource;
 mod route;
+mod url;
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct Router {
 	routes: Vec<SerializedRoute>,
+	middleware_groups: MiddlewareGroups,
+}
+
+/// The function building the application's [`Router`], along with the path Pavex calls it by.
+/// Built with the [`routes!`](crate::routes) macro.
+pub struct Routes {
+	#[doc(hidden)]
+	pub build: fn() -> Router,
+	#[doc(hidden)]
+	pub callable: RawCallable,
+}
+
+/// Name the function building the application's router, for [`Application::routes`](crate::Application::routes).
+#[macro_export]
+macro_rules! routes {
+	($($path:tt)+) => {
+		$crate::router::Routes {
+			build: $($path)+,
+			callable: $crate::f!($($path)+),
+		}
+	};
 }
 
 impl Router {
@@ -31,6 +62,19 @@ impl Router {
 		Self::default()
 	}
 
+	/// Register named groups of middleware, which routes can use like a single middleware.
+	pub fn middleware_groups<
+		'a,
+		I: IntoIterator<Item = (&'a &'static str, &'a &'static [&'static str])>,
+	>(
+		&mut self,
+		groups: I,
+	) -> &mut Self {
+		self.middleware_groups.extend(collect_groups(groups));
+
+		self
+	}
+
 	pub fn name(&mut self, name: &str) -> Builder<'_> {
 		Builder {
 			path: None,
@@ -108,48 +152,138 @@ impl Router {
 		.api()
 	}
 
+	/// The routes registered so far.
+	pub fn routes(&self) -> &[SerializedRoute] {
+		&self.routes
+	}
+
+	/// The route matching `method` and `path`, where `path` is the route's pattern, as exposed by
+	/// `MatchedPathPattern`.
+	pub fn route(&self, method: &Method, path: &str) -> Option<&SerializedRoute> {
+		self.routes
+			.iter()
+			.find(|route| &route.method == method && route.path == path)
+	}
+
+	/// Check the registered routes for duplicate method and path pairs, duplicate names and malformed parameters.
+	///
+	/// # Errors
+	///
+	/// Returns every problem found, each pointing at the place the route was defined.
+	pub fn validate(&self) -> Result<(), Vec<RouteError>> {
+		let mut errors = Vec::new();
+		let mut paths = HashMap::new();
+		let mut names = HashMap::new();
+
+		for route in &self.routes {
+			let location = &route.request_handler.location;
+
+			match paths.entry((route.method.clone(), route.path.clone())) {
+				Entry::Vacant(entry) => {
+					entry.insert(location);
+				},
+				Entry::Occupied(entry) => errors.push(RouteError::DuplicateRoute {
+					method: route.method.clone(),
+					path: route.path.clone(),
+					first: SourceLocation::from(*entry.get()),
+					second: SourceLocation::from(location),
+				}),
+			}
+
+			if let Some(name) = &route.name {
+				match names.entry(name.clone()) {
+					Entry::Vacant(entry) => {
+						entry.insert(location);
+					},
+					Entry::Occupied(entry) => errors.push(RouteError::DuplicateName {
+						name: name.clone(),
+						first: SourceLocation::from(*entry.get()),
+						second: SourceLocation::from(location),
+					}),
+				}
+			}
+
+			let mut parameters = Vec::new();
+			for segment in route.path.split('/') {
+				let Some(index) = segment.find(':') else {
+					continue;
+				};
+
+				let parameter = &segment[index + 1..];
+				let is_valid = index == 0
+					&& !parameter.is_empty()
+					&& parameter
+						.chars()
+						.all(|c| c.is_ascii_alphanumeric() || c == '_')
+					&& !parameters.contains(&parameter);
+
+				if !is_valid {
+					errors.push(RouteError::MalformedParameter {
+						path: route.path.clone(),
+						segment: segment.to_string(),
+						location: SourceLocation::from(location),
+					});
+				}
+
+				parameters.push(parameter);
+			}
+		}
+
+		if errors.is_empty() {
+			Ok(())
+		} else {
+			Err(errors)
+		}
+	}
+
 	pub(crate) fn register(
 		mut self,
 		blueprint: &mut Blueprint,
-		middleware: &HashMap<String, RegisteredCallable>,
+		registered_middleware: &HashMap<String, RegisteredWrappingMiddleware>,
 	) {
+		if let Err(errors) = self.validate() {
+			panic!(
+				"The router has invalid routes:\n{}",
+				errors
+					.iter()
+					.map(|error| format!("  - {error}"))
+					.collect::<Vec<_>>()
+					.join("\n")
+			);
+		}
+
 		for route in self.routes.drain(..) {
-			let registered_route = RegisteredRoute {
-				path: route.path.clone(),
-				method_guard: route.method.into(),
-				request_handler: route.request_handler,
-				error_handler: route.error_handler,
-			};
-
-			if route.middleware.is_empty() {
+			let resolved = middleware::resolve(
+				&route.middleware,
+				&route.excluded_middleware,
+				&self.middleware_groups,
+			);
+
+			let path = route.path.clone();
+			let registered_route = RegisteredRoute::from(route);
+
+			if resolved.is_empty() {
 				blueprint.routes.push(registered_route);
 				continue;
 			}
 
-			let mut middleware = route
-				.middleware
-				.into_iter()
-				.map(|name| {
-					middleware.get(&name).unwrap_or_else(|| {
-						panic!(
-							"Route [{}] expects middleware `{name}`, but it is not registered.",
-							route.path
-						)
-					})
-				})
-				.collect::<Vec<_>>();
-
 			let mut nested_bp = Blueprint::new();
 			nested_bp.routes.push(registered_route);
 
-			for middleware in middleware.drain(..) {
+			for reference in resolved {
+				let registered = registered_middleware
+					.get(&reference.name)
+					.unwrap_or_else(|| {
+						panic!(
+							"Route [{path}] expects middleware `{}`, but it is not registered.",
+							reference.name
+						)
+					});
+
 				nested_bp.middlewares.push(RegisteredWrappingMiddleware {
-					middleware: RegisteredCallable {
-						callable: middleware.callable.clone(),
-						location: middleware.location.clone(),
-					},
-					error_handler: None,
-				})
+					middleware: clone_callable(&registered.middleware),
+					error_handler: registered.error_handler.as_ref().map(clone_callable),
+				});
 			}
 
 			blueprint.nest(nested_bp);
@@ -159,6 +293,96 @@ impl Router {
 	fn store_route(&mut self, route: SerializedRoute) {
 		self.routes.push(route);
 	}
+
+	/// Serialize the routes as they are registered with Pavex, as pretty-printed JSON.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the routes can't be serialized.
+	pub fn to_json(&self) -> serde_json::Result<String> {
+		let routes = self
+			.routes
+			.iter()
+			.cloned()
+			.map(RegisteredRoute::from)
+			.collect::<Vec<_>>();
+
+		Ok(format!("{}\n", serde_json::to_string_pretty(&routes)?))
+	}
+}
+
+impl From<SerializedRoute> for RegisteredRoute {
+	fn from(route: SerializedRoute) -> Self {
+		Self {
+			path: route.path,
+			method_guard: route.method.into(),
+			request_handler: route.request_handler,
+			error_handler: route.error_handler,
+		}
+	}
+}
+
+fn clone_callable(callable: &RegisteredCallable) -> RegisteredCallable {
+	RegisteredCallable {
+		callable: callable.callable.clone(),
+		location: callable.location.clone(),
+	}
+}
+
+/// Join two path fragments, collapsing repeated slashes and dropping trailing ones.
+pub(crate) fn join_paths(prefix: &str, path: &str) -> String {
+	let segments = prefix
+		.split('/')
+		.chain(path.split('/'))
+		.filter(|segment| !segment.is_empty())
+		.collect::<Vec<_>>();
+
+	format!("/{}", segments.join("/"))
+}
+
+/// A problem with the routes registered on a [`Router`].
+#[derive(Debug, thiserror::Error)]
+pub enum RouteError {
+	#[error("{method} {path} is defined twice, at {first} and {second}")]
+	DuplicateRoute {
+		method: Method,
+		path: String,
+		first: SourceLocation,
+		second: SourceLocation,
+	},
+
+	#[error("route name [{name}] is used twice, at {first} and {second}")]
+	DuplicateName {
+		name: String,
+		first: SourceLocation,
+		second: SourceLocation,
+	},
+
+	#[error("route {path} at {location} has a malformed parameter `{segment}`")]
+	MalformedParameter {
+		path: String,
+		segment: String,
+		location: SourceLocation,
+	},
+}
+
+/// Where a route was defined, as `file:line:column`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation(String);
+
+impl From<&Location> for SourceLocation {
+	fn from(location: &Location) -> Self {
+		Self(format!(
+			"{}:{}:{}",
+			location.file, location.line, location.column
+		))
+	}
+}
+
+impl Display for SourceLocation {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(&self.0)
+	}
 }
 
 macro_rules! impl_method {
@@ -169,7 +393,7 @@ macro_rules! impl_method {
 				Route {
 					router: self,
 					route: SerializedRoute {
-						path: path.to_string(),
+						path: join_paths("", path),
 						method: Method::$method,
 						request_handler: RegisteredCallable {
 							location: std::panic::Location::caller().into(),
@@ -5,7 +5,7 @@ use pavex::{
 	http::Method,
 };
 
-use super::{builder::OneOrMultiple, route::SerializedRoute, Router};
+use super::{builder::OneOrMultiple, join_paths, route::SerializedRoute, Router};
 
 pub struct Resource<'r> {
 	pub(crate) router: &'r mut Router,
@@ -26,6 +26,7 @@ impl<'r> Resource<'r> {
 				callable,
 				path: path.to_string(),
 				middleware: Vec::new(),
+				excluded_middleware: Vec::new(),
 				name: name.to_string(),
 				methods: vec![
 					ResourceMethod::Index,
@@ -74,6 +75,13 @@ impl<'r> Resource<'r> {
 		self
 	}
 
+	/// Opt the resource's routes out of middleware inherited from their groups.
+	pub fn without_middleware<M: OneOrMultiple>(&mut self, middleware: M) -> &mut Self {
+		self.resource.excluded_middleware = middleware.get();
+
+		self
+	}
+
 	pub fn name(&mut self, name: &str) -> &mut Self {
 		self.resource.name = name.to_string();
 
@@ -90,6 +98,7 @@ impl<'r> Drop for Resource<'r> {
 				&self.resource.callable,
 			);
 			route.middleware = self.resource.middleware.clone();
+			route.excluded_middleware = self.resource.excluded_middleware.clone();
 
 			self.router.store_route(route)
 		}
@@ -100,6 +109,7 @@ pub struct SerializedResource {
 	pub(crate) name: String,
 	pub(crate) path: String,
 	pub(crate) middleware: Vec<String>,
+	pub(crate) excluded_middleware: Vec<String>,
 	pub(crate) methods: Vec<ResourceMethod>,
 	pub(crate) callable: RegisteredCallable,
 }
@@ -120,11 +130,11 @@ impl ResourceMethod {
 		let singular_resource = pluralizer::pluralize(name, 1, false);
 
 		let path = match self {
-			Self::Create => format!("{path}/create"),
-			Self::Index | Self::Store => path.to_string(),
-			Self::Edit => format!("{path}/:{singular_resource}/edit"),
+			Self::Create => join_paths(path, "create"),
+			Self::Index | Self::Store => join_paths(path, ""),
+			Self::Edit => join_paths(path, &format!(":{singular_resource}/edit")),
 			Self::Show | Self::Update | Self::Destroy => {
-				format!("{path}/:{singular_resource}")
+				join_paths(path, &format!(":{singular_resource}"))
 			},
 		};
 
@@ -140,6 +150,7 @@ impl ResourceMethod {
 			method,
 			error_handler: None,
 			middleware: Vec::new(),
+			excluded_middleware: Vec::new(),
 			name: Some(format!("{name}.{self}")),
 			request_handler: RegisteredCallable {
 				location: callable.location.clone(),
@@ -28,6 +28,13 @@ impl<'r> Route<'r> {
 		self
 	}
 
+	/// Opt this route out of middleware inherited from its groups.
+	pub fn without_middleware<M: OneOrMultiple>(&mut self, middleware: M) -> &mut Self {
+		self.route.excluded_middleware = middleware.get();
+
+		self
+	}
+
 	#[track_caller]
 	pub fn error_handler(&mut self, callable: RawCallable) -> &mut Self {
 		self.route.error_handler = Some(RegisteredCallable {
@@ -50,10 +57,31 @@ pub struct SerializedRoute {
 	pub(crate) method: Method,
 	pub(crate) name: Option<String>,
 	pub(crate) middleware: Vec<String>,
+	/// Group middleware this route opted out of.
+	pub(crate) excluded_middleware: Vec<String>,
 	pub(crate) request_handler: RegisteredCallable,
 	pub(crate) error_handler: Option<RegisteredCallable>,
 }
 
+impl SerializedRoute {
+	pub fn path(&self) -> &str {
+		&self.path
+	}
+
+	pub fn method(&self) -> &Method {
+		&self.method
+	}
+
+	pub fn name(&self) -> Option<&str> {
+		self.name.as_deref()
+	}
+
+	/// The names of the middleware wrapping this route, outermost first.
+	pub fn middleware(&self) -> &[String] {
+		&self.middleware
+	}
+}
+
 impl Clone for SerializedRoute {
 	fn clone(&self) -> Self {
 		Self {
@@ -61,6 +89,7 @@ impl Clone for SerializedRoute {
 			name: self.name.clone(),
 			method: self.method.clone(),
 			middleware: self.middleware.clone(),
+			excluded_middleware: self.excluded_middleware.clone(),
 			request_handler: RegisteredCallable {
 				callable: self.request_handler.callable.clone(),
 				location: self.request_handler.location.clone(),
@@ -84,6 +113,7 @@ impl Default for SerializedRoute {
 			method: Method::GET,
 			error_handler: None,
 			middleware: Vec::new(),
+			excluded_middleware: Vec::new(),
 			request_handler: RegisteredCallable {
 				location: Location {
 					line: line!(),
@@ -96,6 +126,7 @@ impl Default for SerializedRoute {
 	}
 }
 
+#[derive(Debug, Clone)]
 pub struct NamedRoutes(HashMap<String, String>);
 
 impl From<&[SerializedRoute]> for NamedRoutes {
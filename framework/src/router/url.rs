@@ -0,0 +1,139 @@
+use pavex::response::{IntoResponse, Response};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use super::{route::NamedRoutes, Router};
+
+/// Characters escaped when a parameter is placed in a path segment.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+	.remove(b'-')
+	.remove(b'.')
+	.remove(b'_')
+	.remove(b'~');
+
+/// Generates URLs for named routes, so handlers don't hard-code paths.
+#[derive(Debug, Clone)]
+pub struct UrlGenerator {
+	routes: NamedRoutes,
+}
+
+impl UrlGenerator {
+	/// Collect the named routes of the given router.
+	pub fn new(router: &Router) -> Self {
+		Self::from(router)
+	}
+
+	/// Build the URL for the route with the given name.
+	///
+	/// Parameters matching a `:param` or `*catchall` segment of the route are placed in the path, the rest
+	/// are appended as a query string. Catch-all values may span several segments, so their slashes are kept.
+	///
+	/// # Errors
+	///
+	/// Returns an error if no route has the given name, or a path parameter is missing.
+	pub fn route(&self, name: &str, params: &[(&str, &str)]) -> Result<String, UrlError> {
+		let path = self
+			.routes
+			.get(name)
+			.ok_or_else(|| UrlError::UnknownRoute(name.to_string()))?;
+
+		let mut used = vec![false; params.len()];
+		let path = path
+			.split('/')
+			.map(|segment| {
+				let (param, is_catchall) =
+					match (segment.strip_prefix(':'), segment.strip_prefix('*')) {
+						(Some(param), _) => (param, false),
+						(_, Some(param)) => (param, true),
+						_ => return Ok(segment.to_string()),
+					};
+
+				let index = params
+					.iter()
+					.position(|(key, _)| *key == param)
+					.ok_or_else(|| UrlError::MissingParameter {
+						route: name.to_string(),
+						parameter: param.to_string(),
+					})?;
+				used[index] = true;
+
+				if is_catchall {
+					return Ok(params[index]
+						.1
+						.split('/')
+						.map(|part| utf8_percent_encode(part, PATH_SEGMENT).to_string())
+						.collect::<Vec<_>>()
+						.join("/"));
+				}
+
+				Ok(utf8_percent_encode(params[index].1, PATH_SEGMENT).to_string())
+			})
+			.collect::<Result<Vec<_>, UrlError>>()?
+			.join("/");
+
+		let query = params
+			.iter()
+			.zip(used)
+			.filter(|(_, used)| !used)
+			.map(|(param, _)| *param)
+			.collect::<Vec<_>>();
+
+		if query.is_empty() {
+			return Ok(path);
+		}
+
+		let query = form_urlencoded::Serializer::new(String::new())
+			.extend_pairs(query)
+			.finish();
+
+		Ok(format!("{path}?{query}"))
+	}
+}
+
+impl UrlGenerator {
+	/// Render the named routes as a TypeScript module exporting a type-checked `route()` helper.
+	#[must_use]
+	pub fn to_typescript(&self) -> String {
+		let mut routes = self.routes.iter().collect::<Vec<_>>();
+		routes.sort();
+
+		let table = routes
+			.into_iter()
+			.map(|(name, path)| {
+				format!(
+					"\t{}: {},\n",
+					serde_json::Value::from(name.as_str()),
+					serde_json::Value::from(path.as_str())
+				)
+			})
+			.collect::<String>();
+
+		include_str!("url.ts").replace("__ROUTES__\n", &table)
+	}
+}
+
+impl From<&Router> for UrlGenerator {
+	fn from(router: &Router) -> Self {
+		Self {
+			routes: NamedRoutes::from(router.routes.as_slice()),
+		}
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UrlError {
+	#[error("route [{0}] is not defined")]
+	UnknownRoute(String),
+
+	#[error("missing parameter `{parameter}` for route [{route}]")]
+	MissingParameter { route: String, parameter: String },
+}
+
+impl IntoResponse for UrlError {
+	/// Log the error and respond with a `500 Internal Server Error`: a route that can't be generated is a bug,
+	/// not something the client can fix.
+	fn into_response(self) -> Response {
+		tracing::error!(error.msg = %self, error.details = ?self, "Failed to generate a URL");
+
+		Response::internal_server_error()
+	}
+}
@@ -9,8 +9,7 @@ use tracing::Instrument;
 
 /// A logging middleware that wraps the request pipeline in the root span.
 /// It takes care to record key information about the request and the response.
-#[allow(clippy::future_not_send)]
-pub async fn logger<C: IntoFuture<Output = Response>>(
+pub async fn logger<C: IntoFuture<Output = Response, IntoFuture: Send> + Send>(
 	next: Next<C>,
 	root_span: RootSpan,
 ) -> Response {
@@ -0,0 +1,170 @@
+use framework::{
+	f,
+	http::Method,
+	router::{MiddlewareReference, RouteError, RouteMiddleware, Router, UrlGenerator},
+};
+
+#[test]
+fn prefixes_and_paths_are_joined_with_single_slashes() {
+	let mut router = Router::new();
+
+	router.name("auth.").prefix("/auth/").group(|mut router| {
+		router.get("/login/", f!(crate::login)).name("login");
+		router
+			.resource("users/", f!(crate::UserController))
+			.only(vec!["show"]);
+	});
+
+	assert!(router.validate().is_ok());
+
+	let urls = UrlGenerator::from(&router);
+	assert_eq!(urls.route("auth.login", &[]).unwrap(), "/auth/login");
+	assert_eq!(
+		urls.route("auth.users.show", &[("user", "1")]).unwrap(),
+		"/auth/users/1"
+	);
+
+	let route = router.route(&Method::GET, "/auth/users/:user").unwrap();
+	assert_eq!(route.name(), Some("auth.users.show"));
+	assert!(router.route(&Method::POST, "/auth/login").is_none());
+}
+
+#[test]
+fn duplicate_and_malformed_routes_are_reported() {
+	let mut router = Router::new();
+
+	router.get("/users", f!(crate::index)).name("users");
+	router.get("/users/", f!(crate::index)).name("users");
+	router.get("/users/:id/:id", f!(crate::show));
+	router.get("/posts/:", f!(crate::show));
+	router.get("/posts/draft:id", f!(crate::show));
+
+	let errors = router.validate().unwrap_err();
+
+	assert!(matches!(errors[0], RouteError::DuplicateRoute { .. }));
+	assert!(matches!(errors[1], RouteError::DuplicateName { .. }));
+	assert!(errors[2..]
+		.iter()
+		.all(|error| matches!(error, RouteError::MalformedParameter { .. })));
+	assert_eq!(errors.len(), 5);
+	assert!(errors[0].to_string().contains("tests/router.rs:"));
+}
+
+#[test]
+fn routes_are_listed_as_json_with_their_normalized_paths() {
+	let mut router = Router::new();
+
+	router.prefix("/auth/").group(|mut router| {
+		router.delete("/logout/", f!(crate::logout));
+	});
+
+	let routes: serde_json::Value = serde_json::from_str(&router.to_json().unwrap()).unwrap();
+
+	assert_eq!(routes[0]["path"], "/auth/logout");
+	assert_eq!(
+		routes[0]["request_handler"]["callable"]["import_path"],
+		"crate::logout"
+	);
+	assert!(routes[0]["request_handler"]["location"]["file"]
+		.as_str()
+		.unwrap()
+		.ends_with("tests/router.rs"));
+}
+
+#[test]
+fn nested_groups_compose_prefixes_names_and_middleware() {
+	let mut router = Router::new();
+
+	router
+		.name("admin.")
+		.prefix("/admin")
+		.middleware(vec!["web", "auth"])
+		.group(|mut router| {
+			router
+				.name("users.")
+				.prefix("/users")
+				.middleware(vec!["can:manage", "throttle:60,1"])
+				.group(|mut router| {
+					router.get("/", f!(crate::index)).name("index");
+					router
+						.post("/import", f!(crate::import))
+						.name("import")
+						.middleware(vec!["auth", "throttle"])
+						.without_middleware("web");
+				});
+		});
+
+	let routes = router.routes();
+	let index = routes
+		.iter()
+		.find(|route| route.name() == Some("admin.users.index"))
+		.unwrap();
+	assert_eq!(index.path(), "/admin/users");
+	assert_eq!(
+		index.middleware(),
+		["web", "auth", "can:manage", "throttle:60,1"]
+	);
+
+	let import = routes
+		.iter()
+		.find(|route| route.name() == Some("admin.users.import"))
+		.unwrap();
+	assert_eq!(import.path(), "/admin/users/import");
+	assert_eq!(
+		import.middleware(),
+		["auth", "can:manage", "throttle"],
+		"the route's own arguments replace the group's"
+	);
+}
+
+#[test]
+fn middleware_groups_expand_and_pass_arguments() {
+	let mut router = Router::new();
+
+	router.prefix("/api").middleware("api").group(|mut router| {
+		router.get("/users", f!(crate::index));
+		router
+			.get("/users/export", f!(crate::export))
+			.middleware("throttle:5,10");
+		router
+			.post("/users", f!(crate::store))
+			.middleware("can:create,user")
+			.without_middleware("throttle");
+	});
+
+	let groups: [(&str, &[&str]); 2] = [
+		("api", &["token", "throttle:60,1", "web"]),
+		("web", &["session", "token"]),
+	];
+	router.middleware_groups(groups.iter().map(|(name, group)| (name, group)));
+	let middleware = RouteMiddleware::new(&router);
+
+	assert_eq!(
+		middleware.arguments(&Method::GET, "/api/users", "throttle"),
+		Some(["60".to_string(), "1".to_string()].as_slice())
+	);
+	assert_eq!(
+		middleware.arguments(&Method::GET, "/api/users/export", "throttle"),
+		Some(["5".to_string(), "10".to_string()].as_slice()),
+		"arguments on the route win over the group's"
+	);
+	assert_eq!(
+		middleware.arguments(&Method::GET, "/api/users", "session"),
+		Some([].as_slice())
+	);
+	assert_eq!(
+		middleware.arguments(&Method::POST, "/api/users", "can"),
+		Some(["create".to_string(), "user".to_string()].as_slice())
+	);
+	assert_eq!(
+		middleware.arguments(&Method::POST, "/api/users", "throttle"),
+		None
+	);
+	assert_eq!(
+		MiddlewareReference::parse("auth"),
+		MiddlewareReference {
+			name: "auth".to_string(),
+			arguments: Vec::new(),
+		}
+	);
+}
@@ -0,0 +1,95 @@
+use framework::{
+	f,
+	http::{header, response::IntoResponse, Redirect, StatusCode},
+	router::{Router, UrlError, UrlGenerator},
+};
+
+fn router() -> Router {
+	let mut router = Router::new();
+
+	router
+		.get("/users/:user", f!(crate::show_user))
+		.name("users.show");
+
+	router
+		.get("/files/*path", f!(crate::show_file))
+		.name("files.show");
+
+	router.name("auth.").prefix("/auth").group(|mut router| {
+		router.delete("/logout", f!(crate::logout)).name("logout");
+	});
+
+	router
+}
+
+#[test]
+fn named_routes_generate_urls() {
+	let urls = UrlGenerator::from(&router());
+
+	assert_eq!(urls.route("auth.logout", &[]).unwrap(), "/auth/logout");
+	assert_eq!(
+		urls.route("users.show", &[("user", "jane doe"), ("tab", "posts")])
+			.unwrap(),
+		"/users/jane%20doe?tab=posts"
+	);
+}
+
+#[test]
+fn catchall_parameters_keep_their_slashes() {
+	let urls = UrlGenerator::from(&router());
+
+	assert_eq!(
+		urls.route("files.show", &[("path", "docs/read me.md")])
+			.unwrap(),
+		"/files/docs/read%20me.md"
+	);
+	assert!(matches!(
+		urls.route("files.show", &[]),
+		Err(UrlError::MissingParameter { .. })
+	));
+}
+
+#[test]
+fn unknown_routes_and_missing_parameters_are_errors() {
+	let urls = UrlGenerator::from(&router());
+
+	assert!(matches!(
+		urls.route("missing", &[]),
+		Err(UrlError::UnknownRoute(_))
+	));
+	assert!(matches!(
+		urls.route("users.show", &[]),
+		Err(UrlError::MissingParameter { .. })
+	));
+}
+
+#[test]
+fn url_errors_respond_with_a_server_error() {
+	let response = UrlError::UnknownRoute("missing".to_string()).into_response();
+
+	assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[test]
+fn redirects_to_named_routes_are_followed_with_a_get() {
+	let response = Redirect::route(&UrlGenerator::from(&router()), "auth.logout", &[]).unwrap();
+
+	assert_eq!(response.status(), StatusCode::SEE_OTHER);
+	assert_eq!(
+		response.headers().get(header::LOCATION).unwrap(),
+		"/auth/logout"
+	);
+}
+
+#[test]
+fn named_routes_are_exported_as_typescript() {
+	let typescript = UrlGenerator::from(&router()).to_typescript();
+
+	assert!(typescript.contains(concat!(
+		"\t\"auth.logout\": \"/auth/logout\",\n",
+		"\t\"files.show\": \"/files/*path\",\n",
+		"\t\"users.show\": \"/users/:user\",\n",
+		"} as const"
+	)));
+	assert!(typescript.contains("export function route<Name extends RouteName>"));
+}
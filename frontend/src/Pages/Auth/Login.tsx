@@ -1,4 +1,5 @@
 import Logo from '@/Shared/Logo'
+import { route } from '@/routes'
 import TextInput from '@/Shared/TextInput'
 import { FormEvent, useCallback } from 'react'
 import LoadingButton from '@/Shared/LoadingButton'
@@ -16,7 +17,7 @@ const Login = () => {
 		(event: FormEvent<HTMLFormElement>) => {
 			event.preventDefault()
 
-			post('/auth/login')
+			post(route('auth.login.store'))
 		},
 		[post]
 	)
@@ -0,0 +1,20 @@
+import Logo from '@/Shared/Logo'
+import { route } from '@/routes'
+import { Head, router } from '@inertiajs/react'
+
+const Dashboard = () => (
+	<>
+		<Head title="Dashboard" />
+		<div className="flex items-center justify-center p-6 min-h-screen bg-indigo-800">
+			<div className="w-full max-w-md text-center">
+				<Logo className="block mx-auto w-full max-w-xs fill-white" height="50" />
+				<h1 className="mt-8 text-3xl font-bold text-white">Dashboard</h1>
+				<button onClick={() => router.delete(route('auth.logout'))} className="btn-indigo mt-8" type="button">
+					Logout
+				</button>
+			</div>
+		</div>
+	</>
+)
+
+export default Dashboard
@@ -0,0 +1,46 @@
+// This file is generated by `sdk-builder` from the application's named routes. Do not edit it by hand.
+
+export const routes = {
+	"api.user": "/api/user",
+	"auth.login.index": "/auth/login",
+	"auth.login.store": "/auth/login",
+	"auth.logout": "/auth/logout",
+	"dashboard": "/dashboard",
+	"health-check": "/healthz",
+} as const
+
+type Routes = typeof routes
+type Value = string | number
+type Query = Record<string, Value>
+
+export type RouteName = keyof Routes
+
+/** The `:param` and `*catchall` segments of a route path. */
+type Params<Path extends string> = Path extends `${infer Head}*${infer Catchall}`
+	? Params<Head> | Catchall
+	: Path extends `${string}:${infer Param}/${infer Rest}`
+	? Param | Params<Rest>
+	: Path extends `${string}:${infer Param}`
+	? Param
+	: never
+
+type RouteArgs<Name extends RouteName> = [Params<Routes[Name]>] extends [never]
+	? [params?: Query]
+	: [params: Record<Params<Routes[Name]>, Value> & Query]
+
+/** Build the URL for a named route. Parameters not used by the path are appended as a query string. */
+export function route<Name extends RouteName>(name: Name, ...[params]: RouteArgs<Name>): string {
+	const query: Query = { ...params }
+
+	const path = (routes[name] as string).replace(/([:*])(\w+)/g, (_, kind: string, key: string) => {
+		const value = String(query[key])
+		delete query[key]
+
+		// Catch-all values may span several segments, so their slashes are kept.
+		return kind === '*' ? value.split('/').map(encodeURIComponent).join('/') : encodeURIComponent(value)
+	})
+
+	const search = new URLSearchParams(Object.entries(query).map(([key, value]) => [key, String(value)])).toString()
+
+	return search ? `${path}?${search}` : path
+}
@@ -1,9 +1,17 @@
 #![allow(clippy::module_name_repetitions)]
 
-use std::{path::PathBuf, time::Duration};
+use std::{net::IpAddr, path::PathBuf, time::Duration};
+
+use cookie::Cookie;
+use globset::{GlobBuilder, GlobMatcher};
+use pavex::http::Uri;
 
 #[derive(Debug, Clone, serde::Deserialize)]
+#[serde(remote = "Self")]
 pub struct SessionConfig {
+	/// The session storage driver used to persist session data between requests.
+	pub driver: Driver,
+
 	/// The amount of time that you wish the session to be allowed to remain idle before it expires.
 	#[serde(with = "humantime_serde")]
 	pub lifetime: Duration,
@@ -27,17 +35,308 @@ pub struct SessionConfig {
 	pub http_only: bool,
 
 	/// For drivers who need to clean their storage manually, the chance that it will happen on a given request.
+	/// Only used when `sweep_interval` is unset.
 	pub lottery: [u8; 2],
 
+	/// How often a background task purges expired sessions. When unset, garbage collection runs on the `lottery` instead.
+	#[serde(default, with = "humantime_serde")]
+	pub sweep_interval: Option<Duration>,
+
 	/// This option determines how your cookies behave when cross-site requests take place, and can be used to mitigate CSRF attacks.
-	pub same_site: String,
+	pub same_site: SameSite,
+
+	/// If true, the session cookie expires when the browser is closed instead of after `lifetime`.
+	#[serde(default)]
+	pub expire_on_close: bool,
+
+	/// The base64-encoded key used to encrypt session cookies. Required by the `cookie` driver.
+	pub key: Option<String>,
+
+	/// Previous encryption keys, still accepted when reading cookies so keys can be rotated.
+	#[serde(default)]
+	pub previous_keys: Vec<String>,
+
+	/// Proxies allowed to report the client address through `X-Forwarded-For` or `X-Real-IP`.
+	/// The `database` driver records that address with each session; when empty, no address is recorded.
+	/// The connecting peer isn't checked, so only set this when the app can't be reached but through them.
+	#[serde(default)]
+	pub trusted_proxies: Vec<IpAddr>,
+
+	/// The connection settings used by the `redis` driver.
+	pub redis: Option<RedisConfig>,
+
+	/// Whether requests should carry on with an empty session when the session handler fails.
+	#[serde(default)]
+	pub failure_mode: FailureMode,
 
 	/// The CSRF configuration.
 	pub csrf: CsrfConfig,
 }
 
-#[derive(Debug, Clone, Default, serde::Deserialize)]
+/// The storage drivers available to persist session data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Driver {
+	/// Store sessions as JSON files inside `file_location`.
+	File,
+	/// Keep sessions in process memory. Sessions are lost when the process exits.
+	#[serde(alias = "array")]
+	Memory,
+	/// Store the whole session in an encrypted cookie.
+	Cookie,
+	/// Store sessions in the `sessions` database table.
+	Database,
+	/// Store sessions in Redis, letting it expire them natively.
+	Redis,
+}
+
+/// The `SameSite` attribute of the session cookie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SameSite {
+	/// Send the cookie with same-site requests and top-level cross-site navigations.
+	Lax,
+	/// Only send the cookie with same-site requests.
+	Strict,
+	/// Send the cookie with every request. Browsers require `secure` for this to work.
+	None,
+}
+
+impl From<SameSite> for cookie::SameSite {
+	fn from(same_site: SameSite) -> Self {
+		match same_site {
+			SameSite::Lax => Self::Lax,
+			SameSite::Strict => Self::Strict,
+			SameSite::None => Self::None,
+		}
+	}
+}
+
+/// How the session middleware behaves when the session handler fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureMode {
+	/// Log the failure and carry on with an empty session. A session that can't be read is replaced by
+	/// a new one with a fresh ID, leaving the stored one untouched.
+	Open,
+	/// Log the failure and abort the request with a `500 Internal Server Error`.
+	#[default]
+	Closed,
+}
+
+impl<'de> serde::Deserialize<'de> for SessionConfig {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let config = Self::deserialize(deserializer)?;
+
+		// Browsers drop `SameSite=None` cookies that aren't `Secure`, so the session would silently never stick.
+		if config.same_site == SameSite::None && !config.secure {
+			return Err(serde::de::Error::custom(
+				"`same_site: none` requires `secure` to be enabled",
+			));
+		}
+
+		Ok(config)
+	}
+}
+
+impl SessionConfig {
+	/// Build a cookie carrying the configured path, domain, security flags and lifetime.
+	pub(crate) fn build_cookie(&self, name: String, value: String) -> Cookie<'static> {
+		let mut cookie = Cookie::build((name, value))
+			.path(self.path.clone())
+			.secure(self.secure)
+			.http_only(self.http_only)
+			.same_site(self.same_site.into())
+			.build();
+
+		if let Some(domain) = &self.domain {
+			cookie.set_domain(domain.clone());
+		}
+
+		if !self.expire_on_close {
+			cookie.set_max_age(cookie::time::Duration::seconds(
+				i64::try_from(self.lifetime.as_secs()).unwrap_or(i64::MAX),
+			));
+		}
+
+		cookie
+	}
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct RedisConfig {
+	/// The URL of the Redis server, e.g. `redis://127.0.0.1:6379/0`.
+	pub url: String,
+
+	/// The prefix prepended to every session key.
+	#[serde(default = "default_redis_prefix")]
+	pub prefix: String,
+}
+
+fn default_redis_prefix() -> String {
+	"pavex_session:".to_string()
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
 pub struct CsrfConfig {
+	/// Whether state-changing requests must carry a valid CSRF token.
+	#[serde(default = "default_csrf_enabled")]
+	pub enabled: bool,
+
 	/// A list of paths that should be excluded from CSRF protection.
-	pub exclude_paths: Vec<String>,
+	/// Entries are glob patterns (e.g. `/webhooks/*`), matched against both the request path and the matched route template (e.g. `/users/:id`).
+	pub exclude_paths: Vec<PathPattern>,
+
+	/// A list of route names that should be excluded from CSRF protection, as glob patterns (e.g. `webhooks.*`).
+	/// Matched against the [`RouteName`](crate::RouteName) the application provides for the request.
+	#[serde(default)]
+	pub exclude_routes: Vec<NamePattern>,
+
+	/// Whether to also reject state-changing requests whose `Origin`, `Referer` or `Sec-Fetch-Site` headers show a cross-site origin.
+	#[serde(default)]
+	pub verify_origin: bool,
+
+	/// Origins allowed to make cross-site requests when `verify_origin` is on, e.g. `https://app.example.com`.
+	#[serde(default)]
+	pub trusted_origins: Vec<Origin>,
+}
+
+impl Default for CsrfConfig {
+	fn default() -> Self {
+		Self {
+			enabled: default_csrf_enabled(),
+			exclude_paths: Vec::new(),
+			exclude_routes: Vec::new(),
+			verify_origin: false,
+			trusted_origins: Vec::new(),
+		}
+	}
+}
+
+const fn default_csrf_enabled() -> bool {
+	true
+}
+
+/// A glob pattern matched against request paths. `*` matches within a single segment, `**` across segments.
+#[derive(Debug, Clone)]
+pub struct PathPattern(GlobMatcher);
+
+impl PathPattern {
+	/// Compile a path pattern.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the pattern is not a valid glob.
+	pub fn new(pattern: &str) -> Result<Self, globset::Error> {
+		let glob = GlobBuilder::new(pattern).literal_separator(true).build()?;
+
+		Ok(Self(glob.compile_matcher()))
+	}
+
+	/// Whether the given path matches this pattern.
+	#[must_use]
+	pub fn matches(&self, path: &str) -> bool {
+		self.0.is_match(path)
+	}
+}
+
+impl<'de> serde::Deserialize<'de> for PathPattern {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let pattern = String::deserialize(deserializer)?;
+
+		Self::new(&pattern).map_err(serde::de::Error::custom)
+	}
+}
+
+/// The scheme and authority of a URL, e.g. `https://app.example.com`, as browsers send in the `Origin` header.
+/// Hosts are compared case-insensitively, and default ports are dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+	scheme: String,
+	authority: String,
+}
+
+impl Origin {
+	/// Parse an origin, which must have a scheme and a host, and nothing after them.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the origin is missing its scheme or host, or has a path, query or credentials.
+	pub fn new(origin: &str) -> Result<Self, InvalidOrigin> {
+		let invalid = || InvalidOrigin(origin.to_string());
+		let uri = origin.parse::<Uri>().map_err(|_| invalid())?;
+
+		let is_bare = !uri.path_and_query().is_some_and(|path| path != "/")
+			&& !uri
+				.authority()
+				.is_some_and(|authority| authority.as_str().contains('@'));
+
+		if !is_bare {
+			return Err(invalid());
+		}
+
+		Self::from_uri(&uri).ok_or_else(invalid)
+	}
+
+	/// The origin of the given URL, ignoring its path and query.
+	#[must_use]
+	pub fn of_url(url: &str) -> Option<Self> {
+		Self::from_uri(&url.parse().ok()?)
+	}
+
+	fn from_uri(uri: &Uri) -> Option<Self> {
+		let scheme = uri.scheme_str()?.to_ascii_lowercase();
+		let host = uri
+			.host()
+			.filter(|host| !host.is_empty())?
+			.to_ascii_lowercase();
+
+		let authority = match (scheme.as_str(), uri.port_u16()) {
+			(_, None) | ("http", Some(80)) | ("https", Some(443)) => host,
+			(_, Some(port)) => format!("{host}:{port}"),
+		};
+
+		Some(Self { scheme, authority })
+	}
+}
+
+impl<'de> serde::Deserialize<'de> for Origin {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let origin = String::deserialize(deserializer)?;
+
+		Self::new(&origin).map_err(serde::de::Error::custom)
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid origin {0:?}, expected a scheme and host like `https://app.example.com`")]
+pub struct InvalidOrigin(String);
+
+/// A glob pattern matched against route names. Names have no segments, so `*` matches anything, dots included.
+#[derive(Debug, Clone)]
+pub struct NamePattern(GlobMatcher);
+
+impl NamePattern {
+	/// Compile a route name pattern.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the pattern is not a valid glob.
+	pub fn new(pattern: &str) -> Result<Self, globset::Error> {
+		Ok(Self(GlobBuilder::new(pattern).build()?.compile_matcher()))
+	}
+
+	/// Whether the given route name matches this pattern.
+	#[must_use]
+	pub fn matches(&self, name: &str) -> bool {
+		self.0.is_match(name)
+	}
+}
+
+impl<'de> serde::Deserialize<'de> for NamePattern {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let pattern = String::deserialize(deserializer)?;
+
+		Self::new(&pattern).map_err(serde::de::Error::custom)
+	}
 }
@@ -0,0 +1,56 @@
+use std::collections::{BTreeMap, HashMap};
+
+/// Validation messages keyed by the field they refer to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct ErrorBag(BTreeMap<String, Vec<String>>);
+
+impl ErrorBag {
+	/// Create an empty error bag.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add a message for the given field.
+	#[must_use]
+	pub fn with(mut self, field: &str, message: impl Into<String>) -> Self {
+		self.add(field, message);
+		self
+	}
+
+	/// Add a message for the given field.
+	pub fn add(&mut self, field: &str, message: impl Into<String>) {
+		self.0
+			.entry(field.to_string())
+			.or_default()
+			.push(message.into());
+	}
+
+	/// Determine if any field has messages.
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	/// Determine if the given field has messages.
+	pub fn has(&self, field: &str) -> bool {
+		self.0.contains_key(field)
+	}
+
+	/// Get the messages for the given field.
+	pub fn get(&self, field: &str) -> &[String] {
+		self.0.get(field).map_or(&[], Vec::as_slice)
+	}
+
+	/// Get the first message for the given field.
+	pub fn first(&self, field: &str) -> Option<&str> {
+		self.get(field).first().map(String::as_str)
+	}
+
+	/// Get the first message of every field, the shape Inertia forms expect.
+	pub fn first_messages(&self) -> HashMap<String, String> {
+		self.0
+			.iter()
+			.filter_map(|(field, messages)| Some((field.clone(), messages.first()?.clone())))
+			.collect()
+	}
+}
@@ -0,0 +1,200 @@
+use std::{
+	collections::HashMap,
+	sync::Arc,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use cookie::{Cookie, CookieJar, Key};
+use pavex::request::RequestHead;
+
+use super::Handler;
+use crate::{middleware::RequestHeadCookiesExt, SessionConfig};
+
+/// The maximum size, in bytes, browsers are guaranteed to store for a single cookie.
+pub const MAX_COOKIE_SIZE: usize = 4096;
+
+/// The minimum length, in bytes, of a decoded session key.
+const MIN_KEY_LENGTH: usize = 32;
+
+/// A session handler that stores the whole session in an encrypted, authenticated cookie.
+#[derive(Debug, Clone)]
+pub struct CookieHandler {
+	cookie_name: String,
+	valid_for: Duration,
+	config: SessionConfig,
+	/// The key used to encrypt outgoing cookies, followed by the keys that are still accepted.
+	keys: Arc<[Key]>,
+	/// The cookies sent with the current request.
+	request_cookies: CookieJar,
+	/// The attributes read from the current request, kept around to re-issue the cookie on touch.
+	attributes: Option<HashMap<String, serde_json::Value>>,
+	/// The cookies that should be sent back with the response.
+	queued: Vec<Cookie<'static>>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct Payload {
+	id: String,
+	expires_at: u64,
+	attributes: HashMap<String, serde_json::Value>,
+}
+
+impl CookieHandler {
+	/// Create a new cookie handler from the session configuration.
+	///
+	/// # Errors
+	///
+	/// Returns an error if no key is configured or if any configured key is invalid.
+	pub fn new(config: &SessionConfig) -> Result<Self, Error> {
+		let key = config.key.as_deref().ok_or(Error::MissingKey)?;
+
+		let keys = std::iter::once(key)
+			.chain(config.previous_keys.iter().map(String::as_str))
+			.map(parse_key)
+			.collect::<Result<Arc<_>, _>>()?;
+
+		Ok(Self {
+			keys,
+			valid_for: config.lifetime,
+			config: config.clone(),
+			attributes: None,
+			queued: Vec::new(),
+			request_cookies: CookieJar::new(),
+			cookie_name: format!("{}_payload", config.cookie_name),
+		})
+	}
+
+	fn build_cookie(&self, value: String) -> Cookie<'static> {
+		let mut cookie = self.config.build_cookie(self.cookie_name.clone(), value);
+		cookie.set_http_only(true);
+
+		cookie
+	}
+
+	/// Decrypt the session cookie with the current key, falling back to the previous ones.
+	fn decrypt(&self) -> Option<Payload> {
+		let cookie = self.request_cookies.get(&self.cookie_name)?.clone();
+
+		self.keys.iter().find_map(|key| {
+			let cookie = self.request_cookies.private(key).decrypt(cookie.clone())?;
+
+			serde_json::from_str(cookie.value()).ok()
+		})
+	}
+}
+
+impl Handler for CookieHandler {
+	type Error = Error;
+
+	async fn read(&mut self, id: &str) -> Result<HashMap<String, serde_json::Value>, Self::Error> {
+		let Some(payload) = self.decrypt() else {
+			return Ok(HashMap::new());
+		};
+
+		if payload.id != id || payload.expires_at < unix_timestamp() {
+			return Ok(HashMap::new());
+		}
+
+		self.attributes = Some(payload.attributes.clone());
+
+		Ok(payload.attributes)
+	}
+
+	async fn write<T: serde::Serialize + Send>(
+		&mut self,
+		id: &str,
+		attributes: T,
+	) -> Result<(), Self::Error> {
+		let payload = serde_json::to_string(&Payload {
+			id: id.to_string(),
+			expires_at: unix_timestamp() + self.valid_for.as_secs(),
+			attributes: serde_json::from_value(serde_json::to_value(attributes)?)?,
+		})?;
+
+		let mut jar = CookieJar::new();
+		jar.private_mut(&self.keys[0])
+			.add(self.build_cookie(payload));
+
+		let cookie = jar.get(&self.cookie_name).cloned().ok_or(Error::Encrypt)?;
+
+		let size = cookie.encoded().to_string().len();
+		if size > MAX_COOKIE_SIZE {
+			return Err(Error::TooLarge {
+				size,
+				limit: MAX_COOKIE_SIZE,
+			});
+		}
+
+		self.queued.push(cookie);
+
+		Ok(())
+	}
+
+	async fn touch(&mut self, id: &str) -> Result<(), Self::Error> {
+		// The lifetime lives in the cookie itself, so it has to be re-issued.
+		if let Some(attributes) = self.attributes.take() {
+			self.write(id, attributes).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn destroy(&mut self, _id: &str) -> Result<(), Self::Error> {
+		let mut cookie = self.build_cookie(String::new());
+		cookie.make_removal();
+
+		self.queued.push(cookie);
+
+		Ok(())
+	}
+
+	fn set_request(&mut self, request: &RequestHead) {
+		self.request_cookies = request.cookies();
+	}
+
+	fn queued_cookies(&mut self) -> Vec<Cookie<'static>> {
+		std::mem::take(&mut self.queued)
+	}
+}
+
+/// Decode a base64 session key, optionally prefixed with `base64:`.
+fn parse_key(key: &str) -> Result<Key, Error> {
+	let key = BASE64
+		.decode(key.trim_start_matches("base64:"))
+		.map_err(|_| Error::InvalidKey("keys must be base64-encoded".to_string()))?;
+
+	if key.len() < MIN_KEY_LENGTH {
+		return Err(Error::InvalidKey(format!(
+			"keys must be at least {MIN_KEY_LENGTH} bytes long, got {}",
+			key.len()
+		)));
+	}
+
+	Ok(Key::derive_from(&key))
+}
+
+fn unix_timestamp() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs()
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("the cookie session driver requires `session.key` to be set")]
+	MissingKey,
+
+	#[error("invalid session key: {0}")]
+	InvalidKey(String),
+
+	#[error("failed to encrypt the session cookie")]
+	Encrypt,
+
+	#[error("failed to serialize the session payload: {0}")]
+	Serialize(#[from] serde_json::Error),
+
+	#[error("the encrypted session cookie is {size} bytes, which exceeds the {limit} byte cookie limit. Store less data in the session or use a server-side driver")]
+	TooLarge { size: usize, limit: usize },
+}
@@ -0,0 +1,237 @@
+use std::{
+	collections::HashMap,
+	net::IpAddr,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use ensemble::Model;
+use pavex::request::RequestHead;
+
+use super::Handler;
+
+/// The session attribute holding the ID of the authenticated user.
+pub const USER_KEY: &str = "auth.user";
+
+/// A session stored in the `sessions` table.
+#[derive(Debug, Model)]
+#[ensemble(table = "sessions")]
+pub struct StoredSession {
+	pub id: String,
+	pub user_id: Option<u64>,
+	pub ip_address: Option<String>,
+	pub user_agent: Option<String>,
+	pub payload: String,
+	pub last_activity: u64,
+}
+
+/// A session handler that persists sessions in the `sessions` table through `ensemble`.
+#[derive(Debug, Clone)]
+pub struct DatabaseHandler {
+	valid_for: Duration,
+	trusted_proxies: Vec<IpAddr>,
+	ip_address: Option<String>,
+	user_agent: Option<String>,
+}
+
+impl DatabaseHandler {
+	/// Create a database handler. Forwarded client addresses are only read when `trusted_proxies` is set,
+	/// which asserts that every request reaches the application through one of them.
+	pub fn new(valid_for: Duration, trusted_proxies: Vec<IpAddr>) -> Self {
+		Self {
+			valid_for,
+			trusted_proxies,
+			ip_address: None,
+			user_agent: None,
+		}
+	}
+
+	/// The client IP address recorded for the current request, if it could be determined.
+	pub fn ip_address(&self) -> Option<&str> {
+		self.ip_address.as_deref()
+	}
+
+	/// The user agent recorded for the current request.
+	pub fn user_agent(&self) -> Option<&str> {
+		self.user_agent.as_deref()
+	}
+
+	/// List the active sessions of the given user.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the database query fails.
+	pub async fn sessions_for(user_id: u64) -> Result<Vec<StoredSession>, ensemble::Error> {
+		StoredSession::query()
+			.r#where("user_id", '=', user_id)
+			.get::<StoredSession>()
+			.await
+	}
+
+	/// Revoke every session of the given user, returning the number of revoked sessions.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the database query fails.
+	pub async fn revoke_all(user_id: u64) -> Result<u64, ensemble::Error> {
+		StoredSession::query()
+			.r#where("user_id", '=', user_id)
+			.delete()
+			.await
+	}
+
+	async fn find(id: &str) -> Result<Option<StoredSession>, ensemble::Error> {
+		StoredSession::query()
+			.r#where("id", '=', id)
+			.first::<StoredSession>()
+			.await
+	}
+}
+
+impl Handler for DatabaseHandler {
+	type Error = Error;
+
+	async fn read(&mut self, id: &str) -> Result<HashMap<String, serde_json::Value>, Self::Error> {
+		let Some(session) = Self::find(id).await? else {
+			return Ok(HashMap::new());
+		};
+
+		if session.last_activity + self.valid_for.as_secs() < unix_timestamp() {
+			self.destroy(id).await?;
+			return Ok(HashMap::new());
+		}
+
+		Ok(serde_json::from_str(&session.payload)?)
+	}
+
+	async fn write<T: serde::Serialize + Send>(
+		&mut self,
+		id: &str,
+		attributes: T,
+	) -> Result<(), Self::Error> {
+		let attributes = serde_json::to_value(attributes)?;
+		let user_id = attributes.get(USER_KEY).and_then(serde_json::Value::as_u64);
+		let payload = serde_json::to_string(&attributes)?;
+
+		let mut session = match Self::find(id).await? {
+			Some(session) => session,
+			None => {
+				let created = StoredSession::create(StoredSession {
+					user_id,
+					id: id.to_string(),
+					payload: payload.clone(),
+					last_activity: unix_timestamp(),
+					ip_address: self.ip_address.clone(),
+					user_agent: self.user_agent.clone(),
+				})
+				.await;
+
+				match created {
+					Ok(_) => return Ok(()),
+					// A concurrent request inserted the same session since it was looked up, so update that row.
+					Err(e) => Self::find(id).await?.ok_or(e)?,
+				}
+			},
+		};
+
+		session.user_id = user_id;
+		session.payload = payload;
+		session.last_activity = unix_timestamp();
+		session.ip_address = self.ip_address.clone();
+		session.user_agent = self.user_agent.clone();
+
+		session.save().await?;
+
+		Ok(())
+	}
+
+	async fn destroy(&mut self, id: &str) -> Result<(), Self::Error> {
+		StoredSession::query()
+			.r#where("id", '=', id)
+			.delete()
+			.await?;
+
+		Ok(())
+	}
+
+	async fn touch(&mut self, id: &str) -> Result<(), Self::Error> {
+		if let Some(mut session) = Self::find(id).await? {
+			session.last_activity = unix_timestamp();
+			session.save().await?;
+		}
+
+		Ok(())
+	}
+
+	async fn collect_garbage(&mut self, max_lifetime: &Duration) -> Result<u64, Self::Error> {
+		let expired_before = unix_timestamp().saturating_sub(max_lifetime.as_secs());
+
+		Ok(StoredSession::query()
+			.r#where("last_activity", '<', expired_before)
+			.delete()
+			.await?)
+	}
+
+	fn set_request(&mut self, request: &RequestHead) {
+		self.user_agent = request
+			.headers
+			.get("User-Agent")
+			.and_then(|header| header.to_str().ok())
+			.map(ToString::to_string);
+
+		self.ip_address = client_ip(request, &self.trusted_proxies).map(|ip| ip.to_string());
+	}
+}
+
+/// Find the client address in the `X-Forwarded-For` (or `X-Real-IP`) headers set by the trusted proxies.
+///
+/// `X-Forwarded-For` is walked from the right, skipping the trusted proxies, so addresses a client
+/// prepended itself are never used. Without trusted proxies the headers are ignored entirely, as any
+/// client could have set them, and no address is recorded.
+///
+/// The peer address isn't exposed to middleware, so it can't be checked against the trusted proxies:
+/// configuring them asserts the application is only reachable through them. If clients can connect
+/// directly, they can send these headers themselves, and `X-Real-IP` is taken as is.
+fn client_ip(request: &RequestHead, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+	if trusted_proxies.is_empty() {
+		return None;
+	}
+
+	let forwarded = request
+		.headers
+		.get_all("X-Forwarded-For")
+		.iter()
+		.filter_map(|header| header.to_str().ok())
+		.flat_map(|header| header.split(','))
+		.map(|ip| ip.trim().parse::<IpAddr>().ok())
+		.collect::<Vec<_>>();
+
+	if forwarded.is_empty() {
+		return request
+			.headers
+			.get("X-Real-IP")
+			.and_then(|header| header.to_str().ok())
+			.and_then(|ip| ip.trim().parse().ok());
+	}
+
+	forwarded
+		.into_iter()
+		.rev()
+		.find(|ip| !matches!(ip, Some(ip) if trusted_proxies.contains(ip)))
+		.flatten()
+}
+
+fn unix_timestamp() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs()
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("failed to query the sessions table: {0}")]
+	Database(#[from] ensemble::Error),
+
+	#[error("failed to (de)serialize the session payload: {0}")]
+	Payload(#[from] serde_json::Error),
+}
@@ -1,108 +1,418 @@
-use std::{collections::HashMap, fs, io, path::PathBuf, rc::Rc, time::Duration};
+use std::{
+	collections::HashMap,
+	fs,
+	io::{self, Write},
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::{Duration, SystemTime},
+};
 
+use fs2::FileExt;
+use rand::{distributions::Alphanumeric, Rng};
+
+/// A session handler that stores each session as a JSON file inside a directory.
+///
+/// Writes go to a temporary file that is renamed over the session file, so a crash never leaves a
+/// half-written session behind. Each session has a `<id>.lock` file, locked from the moment the session
+/// is read until it is written back, touched or destroyed, so concurrent requests for the same session
+/// take turns instead of overwriting each other's changes. Requests for other sessions aren't affected.
+#[derive(Debug, Clone)]
 pub struct FileHandler {
 	path: PathBuf,
 	valid_for: Duration,
+	/// The session read by this handler, and the lock held on it until it is written back.
+	/// Released when the handler is dropped, so a failed request doesn't keep the session locked.
+	lock: Option<(String, Arc<fs::File>)>,
 }
 
 impl FileHandler {
-	pub fn new(path: PathBuf, valid_for: Duration) -> Self {
-		fs::create_dir_all(&path).unwrap();
+	/// Create a new file handler, making sure the session directory exists.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the session directory can't be created.
+	pub fn new(path: PathBuf, valid_for: Duration) -> Result<Self, Error> {
+		fs::create_dir_all(&path).map_err(|source| Error::CreateDir {
+			source,
+			path: path.clone(),
+		})?;
 
-		Self { path, valid_for }
+		Ok(Self {
+			path,
+			valid_for,
+			lock: None,
+		})
+	}
+
+	/// The path of the file holding the given session.
+	/// IDs are checked before being joined to the directory, so a crafted ID can't point outside of it.
+	fn session_path(&self, id: &str) -> Result<PathBuf, Error> {
+		if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+			return Err(Error::InvalidId(id.to_string()));
+		}
+
+		Ok(self.path.join(id))
+	}
+
+	/// Take the lock held since the given session was read, if any.
+	fn release(&mut self, id: &str) -> Option<Arc<fs::File>> {
+		match self.lock.take() {
+			Some((held, file)) if held == id => Some(file),
+			other => {
+				self.lock = other;
+				None
+			},
+		}
 	}
 }
 
 impl super::Handler for FileHandler {
-	type Error = io::Error;
+	type Error = Error;
 
 	async fn read(&mut self, id: &str) -> Result<HashMap<String, serde_json::Value>, Self::Error> {
-		let path = self.path.join(id);
-
-		let file_contents = match fs::read(&path) {
-			Ok(contents) => contents,
-			Err(e) => {
-				if e.kind() == io::ErrorKind::NotFound {
-					return Ok(HashMap::new());
-				} else {
-					return Err(e);
-				}
-			},
-		};
+		let path = self.session_path(id)?;
+		let valid_for = self.valid_for;
 
-		if fs::metadata(&path)?
-			.modified()?
-			.elapsed()
-			.unwrap_or_default()
-			> self.valid_for
-		{
-			fs::remove_file(&path)?;
-			return Ok(HashMap::new());
-		}
+		// Reading a session again must not wait on the lock this handler already holds.
+		self.lock = None;
 
-		Ok(serde_json::from_slice(file_contents.as_ref()).unwrap_or_default())
+		let (file, attributes) = blocking(move || {
+			let file = lock(&path).map_err(|e| Error::read(&path, e))?;
+
+			Ok((file, read(&path, valid_for)?))
+		})
+		.await?;
+
+		self.lock = Some((id.to_string(), Arc::new(file)));
+
+		Ok(attributes)
 	}
 
-	async fn write<T: serde::Serialize>(
+	async fn write<T: serde::Serialize + Send>(
 		&mut self,
 		id: &str,
 		attributes: T,
 	) -> Result<(), Self::Error> {
-		let path = self.path.join(id);
+		let path = self.session_path(id)?;
+		let contents = serde_json::to_vec(&attributes)?;
+		let held = self.release(id);
 
-		fs::write(
-			path,
-			serde_json::to_vec(&attributes).map_err(io::Error::other)?,
-		)
+		blocking(move || {
+			let _lock = relock(held, &path).map_err(|e| Error::write(&path, e))?;
+
+			write(&path, &contents)
+		})
+		.await
 	}
 
 	async fn destroy(&mut self, id: &str) -> Result<(), Self::Error> {
-		let path = self.path.join(id);
+		let path = self.session_path(id)?;
+		let held = self.release(id);
+
+		blocking(move || {
+			let _lock = relock(held, &path).map_err(|e| Error::delete(&path, e))?;
+
+			remove_if_exists(&path).map_err(|e| Error::delete(&path, e))?;
+			remove_lock_file(&path);
 
-		fs::remove_file(path)
+			Ok(())
+		})
+		.await
+	}
+
+	async fn touch(&mut self, id: &str) -> Result<(), Self::Error> {
+		let path = self.session_path(id)?;
+		let held = self.release(id);
+
+		blocking(move || {
+			let _lock = relock(held, &path).map_err(|e| Error::write(&path, e))?;
+
+			match fs::File::options()
+				.write(true)
+				.open(&path)
+				.and_then(|file| file.set_modified(SystemTime::now()))
+			{
+				Err(e) if e.kind() != io::ErrorKind::NotFound => Err(Error::write(&path, e)),
+				_ => Ok(()),
+			}
+		})
+		.await
 	}
 
 	async fn collect_garbage(&mut self, max_lifetime: &Duration) -> Result<u64, Self::Error> {
-		let expired = fs::read_dir(&self.path)?
-			.flatten()
-			.map(|entry| {
-				let last_modified = entry.metadata()?.modified().map_err(io::Error::other)?;
+		let path = self.path.clone();
+		let max_lifetime = *max_lifetime;
+
+		blocking(move || collect_garbage(&path, max_lifetime)).await
+	}
+}
+
+/// Read a session, with its lock held by the caller.
+fn read(path: &Path, valid_for: Duration) -> Result<HashMap<String, serde_json::Value>, Error> {
+	let file_contents = match fs::read(path) {
+		Ok(contents) => contents,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+		Err(e) => return Err(Error::read(path, e)),
+	};
+
+	if is_expired(path, valid_for).map_err(|e| Error::read(path, e))? {
+		remove_if_exists(path).map_err(|e| Error::delete(path, e))?;
+
+		return Ok(HashMap::new());
+	}
+
+	match serde_json::from_slice(&file_contents) {
+		Ok(attributes) => Ok(attributes),
+		Err(e) => {
+			tracing::warn!(
+				error.msg = %e,
+				error.error_chain = ?e,
+				path = %path.display(),
+				"Discarding a corrupt session file"
+			);
+
+			Ok(HashMap::new())
+		},
+	}
+}
+
+/// Write a session, with its lock held by the caller.
+fn write(path: &Path, contents: &[u8]) -> Result<(), Error> {
+	let temp_path = path.with_extension(format!("{}.{TEMP_EXTENSION}", random_suffix()));
+
+	let result = fs::File::create(&temp_path)
+		.and_then(|mut file| {
+			file.write_all(contents)?;
+			file.sync_all()
+		})
+		.and_then(|()| fs::rename(&temp_path, path));
+
+	if let Err(e) = result {
+		let _ = fs::remove_file(&temp_path);
+		return Err(Error::write(path, e));
+	}
+
+	Ok(())
+}
+
+/// Remove expired sessions, each under its own lock. Sessions locked by an in-flight request are in use,
+/// so they are skipped until the next collection instead of being waited on.
+fn collect_garbage(directory: &Path, max_lifetime: Duration) -> Result<u64, Error> {
+	let mut expired = 0;
+
+	for entry in fs::read_dir(directory)
+		.map_err(|e| Error::list(directory, e))?
+		.flatten()
+	{
+		let path = entry.path();
+		let has_extension = |expected: &str| path.extension().is_some_and(|e| e == expected);
+
+		match is_expired(&path, max_lifetime) {
+			Ok(true) => {},
+			Ok(false) => continue,
+			// Already removed along with its session earlier in this sweep, or by a concurrent request.
+			Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+			Err(e) => return Err(Error::read(&path, e)),
+		}
+
+		// Temporary files left behind by a crash are not sessions, so they aren't counted.
+		if has_extension(TEMP_EXTENSION) {
+			remove_if_exists(&path).map_err(|e| Error::delete(&path, e))?;
+			continue;
+		}
+
+		let session_path = if has_extension(LOCK_EXTENSION) {
+			path.with_extension("")
+		} else {
+			path.clone()
+		};
+
+		let Some(_lock) = try_lock(&session_path).map_err(|e| Error::delete(&path, e))? else {
+			continue;
+		};
+
+		// Lock files are never modified, so they look expired while their session is still alive.
+		// Only the ones left behind by a session that is gone are removed.
+		if has_extension(LOCK_EXTENSION) {
+			if !session_path.exists() {
+				remove_lock_file(&session_path);
+			}
+			continue;
+		}
+
+		// Check again under the lock, in case a request renewed the session since it was listed.
+		if !is_expired(&path, max_lifetime).unwrap_or(false) {
+			continue;
+		}
+
+		remove_if_exists(&path).map_err(|e| Error::delete(&path, e))?;
+		remove_lock_file(&path);
+		expired += 1;
+	}
+
+	Ok(expired)
+}
+
+const LOCK_EXTENSION: &str = "lock";
+const TEMP_EXTENSION: &str = "tmp";
+
+fn is_expired(path: &Path, valid_for: Duration) -> io::Result<bool> {
+	let last_modified = fs::metadata(path)?.modified()?;
+
+	Ok(last_modified.elapsed().unwrap_or_default() > valid_for)
+}
+
+fn lock_path(path: &Path) -> PathBuf {
+	path.with_extension(LOCK_EXTENSION)
+}
+
+/// Take an exclusive advisory lock on the session, released when the returned file is dropped.
+///
+/// Lock files are removed along with their session, so a lock taken on a file that has since been
+/// unlinked doesn't protect anything, and is taken again on the file now at the path.
+fn lock(path: &Path) -> io::Result<fs::File> {
+	loop {
+		let file = open_lock_file(path)?;
+		FileExt::lock_exclusive(&file)?;
+
+		if is_current_lock_file(&file, path)? {
+			return Ok(file);
+		}
+	}
+}
+
+/// Take the session lock if nobody else holds it, without waiting.
+fn try_lock(path: &Path) -> io::Result<Option<fs::File>> {
+	let file = open_lock_file(path)?;
+
+	match FileExt::try_lock_exclusive(&file) {
+		Ok(()) if is_current_lock_file(&file, path)? => Ok(Some(file)),
+		Ok(()) => Ok(None),
+		Err(e) if e.kind() == fs2::lock_contended_error().kind() => Ok(None),
+		Err(e) => Err(e),
+	}
+}
+
+/// Reuse the lock held since the session was read, or take it for the duration of a single operation.
+fn relock(held: Option<Arc<fs::File>>, path: &Path) -> io::Result<Arc<fs::File>> {
+	match held {
+		Some(file) => Ok(file),
+		None => lock(path).map(Arc::new),
+	}
+}
+
+fn open_lock_file(path: &Path) -> io::Result<fs::File> {
+	fs::File::options()
+		.create(true)
+		.truncate(false)
+		.write(true)
+		.open(lock_path(path))
+}
+
+#[cfg(unix)]
+fn is_current_lock_file(file: &fs::File, path: &Path) -> io::Result<bool> {
+	use std::os::unix::fs::MetadataExt;
 
-				if &last_modified.elapsed().unwrap_or_default() > max_lifetime {
-					fs::remove_file(entry.path())?;
-				}
+	let locked = file.metadata()?;
+
+	match fs::metadata(lock_path(path)) {
+		Ok(current) => Ok(current.dev() == locked.dev() && current.ino() == locked.ino()),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+		Err(e) => Err(e),
+	}
+}
+
+/// Open files can't be removed on other platforms, so the locked file is always the current one.
+#[cfg(not(unix))]
+fn is_current_lock_file(_file: &fs::File, _path: &Path) -> io::Result<bool> {
+	Ok(true)
+}
 
-				Ok::<_, io::Error>(())
-			})
-			.collect::<Result<Rc<_>, _>>()?;
+/// Remove the lock file of a session, while holding its lock.
+/// A lock file that can't be removed is only clutter, and is retried by the next garbage collection.
+fn remove_lock_file(path: &Path) {
+	let _ = remove_if_exists(&lock_path(path));
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+	match fs::remove_file(path) {
+		Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+		_ => Ok(()),
+	}
+}
+
+fn random_suffix() -> String {
+	rand::thread_rng()
+		.sample_iter(Alphanumeric)
+		.map(char::from)
+		.take(8)
+		.collect()
+}
 
-		Ok(expired.len() as u64)
+/// Run blocking file IO on tokio's blocking thread pool, off the async executor.
+async fn blocking<T: Send + 'static>(
+	f: impl FnOnce() -> Result<T, Error> + Send + 'static,
+) -> Result<T, Error> {
+	match tokio::task::spawn_blocking(f).await {
+		Ok(result) => result,
+		Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+		Err(_) => Err(Error::Cancelled),
 	}
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-	#[error("failed to initialize Flysystem adapter")]
-	Config(String),
+	#[error("failed to create session directory {}: {source}", path.display())]
+	CreateDir { path: PathBuf, source: io::Error },
 
-	#[error("failed to check if session file exists")]
-	FileExists(String),
+	#[error("failed to read session file {}: {source}", path.display())]
+	Read { path: PathBuf, source: io::Error },
 
-	#[error("failed to get last modified time of session file")]
-	LastModified(String),
+	#[error("failed to write session file {}: {source}", path.display())]
+	Write { path: PathBuf, source: io::Error },
 
-	#[error("failed to get elapsed time since last modified")]
-	SystemTime(#[from] std::time::SystemTimeError),
+	#[error("failed to delete session file {}: {source}", path.display())]
+	Delete { path: PathBuf, source: io::Error },
 
-	#[error("failed to read session file")]
-	Read(String),
+	#[error("failed to list session files in {}: {source}", path.display())]
+	List { path: PathBuf, source: io::Error },
 
-	#[error("failed to write to session file")]
-	Write(String),
+	#[error("failed to serialize the session: {0}")]
+	Serialize(#[from] serde_json::Error),
 
-	#[error("failed to delete session file")]
-	Delete(String),
+	#[error("invalid session ID {0:?}")]
+	InvalidId(String),
 
-	#[error("failed to list session files")]
-	List(String),
+	#[error("the session file operation was cancelled because the runtime is shutting down")]
+	Cancelled,
+}
+
+impl Error {
+	fn read(path: &Path, source: io::Error) -> Self {
+		Self::Read {
+			source,
+			path: path.to_path_buf(),
+		}
+	}
+
+	fn write(path: &Path, source: io::Error) -> Self {
+		Self::Write {
+			source,
+			path: path.to_path_buf(),
+		}
+	}
+
+	fn delete(path: &Path, source: io::Error) -> Self {
+		Self::Delete {
+			source,
+			path: path.to_path_buf(),
+		}
+	}
+
+	fn list(path: &Path, source: io::Error) -> Self {
+		Self::List {
+			source,
+			path: path.to_path_buf(),
+		}
+	}
 }
@@ -0,0 +1,116 @@
+use std::{
+	collections::HashMap,
+	fmt::Debug,
+	sync::{Arc, PoisonError, RwLock},
+	time::{Duration, Instant},
+};
+
+struct Entry {
+	last_activity: Instant,
+	attributes: HashMap<String, serde_json::Value>,
+}
+
+/// A session handler that keeps sessions in memory.
+///
+/// Clones of a handler share its store, so sessions survive the per-request clones made by the
+/// [`Manager`](crate::Manager), but are lost when the process exits. Useful for tests and single-node development.
+#[derive(Clone)]
+pub struct MemoryHandler {
+	valid_for: Duration,
+	sessions: Arc<RwLock<HashMap<String, Entry>>>,
+}
+
+impl MemoryHandler {
+	/// Create a handler with a new, empty store.
+	pub fn new(valid_for: Duration) -> Self {
+		Self {
+			valid_for,
+			sessions: Arc::default(),
+		}
+	}
+}
+
+impl Debug for MemoryHandler {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("MemoryHandler")
+			.field("valid_for", &self.valid_for)
+			.finish_non_exhaustive()
+	}
+}
+
+impl super::Handler for MemoryHandler {
+	type Error = serde_json::Error;
+
+	async fn read(&mut self, id: &str) -> Result<HashMap<String, serde_json::Value>, Self::Error> {
+		let mut sessions = self
+			.sessions
+			.write()
+			.unwrap_or_else(PoisonError::into_inner);
+
+		let Some(entry) = sessions.get(id) else {
+			return Ok(HashMap::new());
+		};
+
+		if entry.last_activity.elapsed() > self.valid_for {
+			sessions.remove(id);
+			return Ok(HashMap::new());
+		}
+
+		Ok(entry.attributes.clone())
+	}
+
+	async fn write<T: serde::Serialize + Send>(
+		&mut self,
+		id: &str,
+		attributes: T,
+	) -> Result<(), Self::Error> {
+		let attributes = serde_json::from_value(serde_json::to_value(attributes)?)?;
+
+		self.sessions
+			.write()
+			.unwrap_or_else(PoisonError::into_inner)
+			.insert(
+				id.to_string(),
+				Entry {
+					attributes,
+					last_activity: Instant::now(),
+				},
+			);
+
+		Ok(())
+	}
+
+	async fn destroy(&mut self, id: &str) -> Result<(), Self::Error> {
+		self.sessions
+			.write()
+			.unwrap_or_else(PoisonError::into_inner)
+			.remove(id);
+
+		Ok(())
+	}
+
+	async fn touch(&mut self, id: &str) -> Result<(), Self::Error> {
+		if let Some(entry) = self
+			.sessions
+			.write()
+			.unwrap_or_else(PoisonError::into_inner)
+			.get_mut(id)
+		{
+			entry.last_activity = Instant::now();
+		}
+
+		Ok(())
+	}
+
+	async fn collect_garbage(&mut self, max_lifetime: &Duration) -> Result<u64, Self::Error> {
+		let mut sessions = self
+			.sessions
+			.write()
+			.unwrap_or_else(PoisonError::into_inner);
+
+		let before = sessions.len();
+		sessions.retain(|_, entry| &entry.last_activity.elapsed() <= max_lifetime);
+
+		Ok((before - sessions.len()) as u64)
+	}
+}
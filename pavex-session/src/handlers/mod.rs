@@ -6,30 +6,168 @@ use std::{
 	time::Duration,
 };
 
+use ::cookie::Cookie;
+use pavex::request::RequestHead;
+
+use crate::{config::Driver, SessionConfig};
+
+pub mod cookie;
+pub mod database;
 pub mod file;
+pub mod memory;
+pub mod redis;
 
+pub use self::cookie::CookieHandler;
+pub use self::redis::RedisHandler;
+pub use database::DatabaseHandler;
 pub use file::FileHandler;
+pub use memory::MemoryHandler;
 
-pub trait Handler {
-	type Error: Debug + Error;
+pub trait Handler: Send + Sync {
+	type Error: Debug + Error + Send + Sync + 'static;
 
 	fn read(
 		&mut self,
 		id: &str,
-	) -> impl Future<Output = Result<HashMap<String, serde_json::Value>, Self::Error>>;
+	) -> impl Future<Output = Result<HashMap<String, serde_json::Value>, Self::Error>> + Send;
 
-	fn write<T: serde::Serialize>(
+	fn write<T: serde::Serialize + Send>(
 		&mut self,
 		id: &str,
 		attributes: T,
-	) -> impl Future<Output = Result<(), Self::Error>>;
+	) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+	fn destroy(&mut self, id: &str) -> impl Future<Output = Result<(), Self::Error>> + Send;
 
-	fn destroy(&mut self, id: &str) -> impl Future<Output = Result<(), Self::Error>>;
+	/// Extend the lifetime of an unchanged session without rewriting its data.
+	fn touch(&mut self, _id: &str) -> impl Future<Output = Result<(), Self::Error>> + Send {
+		future::ready(Ok(()))
+	}
 
 	fn collect_garbage(
 		&mut self,
 		_max_lifetime: &Duration,
-	) -> impl Future<Output = Result<u64, Self::Error>> {
+	) -> impl Future<Output = Result<u64, Self::Error>> + Send {
 		future::ready(Ok(0))
 	}
+
+	/// Give the handler access to the incoming request before the session is read.
+	fn set_request(&mut self, _request: &RequestHead) {}
+
+	/// Take the cookies the handler needs to send back with the response.
+	fn queued_cookies(&mut self) -> Vec<Cookie<'static>> {
+		Vec::new()
+	}
+}
+
+/// Forward a call to the handler selected by the configured driver.
+macro_rules! dispatch {
+	($backend:expr, $handler:ident => $call:expr) => {
+		match $backend {
+			Backend::File($handler) => $call,
+			Backend::Memory($handler) => $call,
+			Backend::Cookie($handler) => $call,
+			Backend::Database($handler) => $call,
+			Backend::Redis($handler) => $call,
+		}
+	};
+}
+
+/// The session handler selected by the `driver` configuration key.
+#[derive(Debug, Clone)]
+pub enum Backend {
+	File(FileHandler),
+	Memory(MemoryHandler),
+	Cookie(CookieHandler),
+	Database(DatabaseHandler),
+	Redis(RedisHandler),
+}
+
+impl Backend {
+	/// Build the handler for the driver configured in the given session config.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the configuration is invalid for the selected driver.
+	pub fn from_config(config: &SessionConfig) -> Result<Self, BackendError> {
+		Ok(match config.driver {
+			Driver::File => Self::File(FileHandler::new(
+				config.file_location.clone(),
+				config.lifetime,
+			)?),
+			Driver::Memory => Self::Memory(MemoryHandler::new(config.lifetime)),
+			Driver::Cookie => Self::Cookie(CookieHandler::new(config)?),
+			Driver::Database => Self::Database(DatabaseHandler::new(
+				config.lifetime,
+				config.trusted_proxies.clone(),
+			)),
+			Driver::Redis => {
+				let redis = config
+					.redis
+					.as_ref()
+					.ok_or(BackendError::MissingRedisConfig)?;
+
+				Self::Redis(
+					RedisHandler::new(redis, config.lifetime).map_err(self::redis::Error::from)?,
+				)
+			},
+		})
+	}
+}
+
+impl Handler for Backend {
+	type Error = BackendError;
+
+	async fn read(&mut self, id: &str) -> Result<HashMap<String, serde_json::Value>, Self::Error> {
+		dispatch!(self, handler => Ok(handler.read(id).await?))
+	}
+
+	async fn write<T: serde::Serialize + Send>(
+		&mut self,
+		id: &str,
+		attributes: T,
+	) -> Result<(), Self::Error> {
+		dispatch!(self, handler => Ok(handler.write(id, attributes).await?))
+	}
+
+	async fn destroy(&mut self, id: &str) -> Result<(), Self::Error> {
+		dispatch!(self, handler => Ok(handler.destroy(id).await?))
+	}
+
+	async fn touch(&mut self, id: &str) -> Result<(), Self::Error> {
+		dispatch!(self, handler => Ok(handler.touch(id).await?))
+	}
+
+	async fn collect_garbage(&mut self, max_lifetime: &Duration) -> Result<u64, Self::Error> {
+		dispatch!(self, handler => Ok(handler.collect_garbage(max_lifetime).await?))
+	}
+
+	fn set_request(&mut self, request: &RequestHead) {
+		dispatch!(self, handler => handler.set_request(request))
+	}
+
+	fn queued_cookies(&mut self) -> Vec<Cookie<'static>> {
+		dispatch!(self, handler => handler.queued_cookies())
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackendError {
+	#[error("file session handler failed: {0}")]
+	File(#[from] file::Error),
+
+	#[error("memory session handler failed: {0}")]
+	Memory(#[from] serde_json::Error),
+
+	#[error("cookie session handler failed: {0}")]
+	Cookie(#[from] self::cookie::Error),
+
+	#[error("database session handler failed: {0}")]
+	Database(#[from] database::Error),
+
+	#[error("redis session handler failed: {0}")]
+	Redis(#[from] self::redis::Error),
+
+	#[error("the redis session driver requires `session.redis` to be set")]
+	MissingRedisConfig,
 }
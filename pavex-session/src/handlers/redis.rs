@@ -0,0 +1,116 @@
+use std::{collections::HashMap, fmt::Debug, sync::Arc, time::Duration};
+
+use redis::{aio::ConnectionManager, Client};
+use tokio::sync::OnceCell;
+
+use crate::config::RedisConfig;
+
+/// A session handler that stores sessions in Redis (or any server speaking its protocol).
+///
+/// Sessions are written with `SETEX` and touched with `EXPIRE`, so Redis expires them on its own and
+/// there's no garbage to collect. The connection is re-established automatically when it drops.
+#[derive(Clone)]
+pub struct RedisHandler {
+	client: Client,
+	prefix: String,
+	valid_for: Duration,
+	connection: Arc<OnceCell<ConnectionManager>>,
+}
+
+impl RedisHandler {
+	/// Create a new Redis handler. The connection is established lazily on first use.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the configured URL is invalid.
+	pub fn new(config: &RedisConfig, valid_for: Duration) -> Result<Self, redis::RedisError> {
+		Ok(Self {
+			valid_for,
+			prefix: config.prefix.clone(),
+			client: Client::open(config.url.as_str())?,
+			connection: Arc::new(OnceCell::new()),
+		})
+	}
+
+	/// Get the shared connection, connecting on first use. If connecting fails, the next call tries again.
+	async fn connection(&self) -> Result<ConnectionManager, redis::RedisError> {
+		self.connection
+			.get_or_try_init(|| ConnectionManager::new(self.client.clone()))
+			.await
+			.cloned()
+	}
+
+	fn key(&self, id: &str) -> String {
+		format!("{}{id}", self.prefix)
+	}
+}
+
+impl Debug for RedisHandler {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("RedisHandler")
+			.field("client", &self.client)
+			.field("prefix", &self.prefix)
+			.field("valid_for", &self.valid_for)
+			.finish_non_exhaustive()
+	}
+}
+
+impl super::Handler for RedisHandler {
+	type Error = Error;
+
+	async fn read(&mut self, id: &str) -> Result<HashMap<String, serde_json::Value>, Self::Error> {
+		let payload: Option<String> = redis::cmd("GET")
+			.arg(self.key(id))
+			.query_async(&mut self.connection().await?)
+			.await?;
+
+		let Some(payload) = payload else {
+			return Ok(HashMap::new());
+		};
+
+		Ok(serde_json::from_str(&payload)?)
+	}
+
+	async fn write<T: serde::Serialize + Send>(
+		&mut self,
+		id: &str,
+		attributes: T,
+	) -> Result<(), Self::Error> {
+		redis::cmd("SETEX")
+			.arg(self.key(id))
+			.arg(self.valid_for.as_secs().max(1))
+			.arg(serde_json::to_string(&attributes)?)
+			.query_async::<_, ()>(&mut self.connection().await?)
+			.await?;
+
+		Ok(())
+	}
+
+	async fn touch(&mut self, id: &str) -> Result<(), Self::Error> {
+		redis::cmd("EXPIRE")
+			.arg(self.key(id))
+			.arg(self.valid_for.as_secs().max(1))
+			.query_async::<_, ()>(&mut self.connection().await?)
+			.await?;
+
+		Ok(())
+	}
+
+	async fn destroy(&mut self, id: &str) -> Result<(), Self::Error> {
+		redis::cmd("DEL")
+			.arg(self.key(id))
+			.query_async::<_, ()>(&mut self.connection().await?)
+			.await?;
+
+		Ok(())
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("failed to talk to the Redis server: {0}")]
+	Redis(#[from] redis::RedisError),
+
+	#[error("failed to (de)serialize the session payload: {0}")]
+	Payload(#[from] serde_json::Error),
+}
@@ -6,22 +6,35 @@ use pavex::{
 	f,
 };
 
-pub use config::{CsrfConfig, SessionConfig};
+pub use config::{
+	CsrfConfig, Driver, FailureMode, InvalidOrigin, NamePattern, Origin, PathPattern, RedisConfig,
+	SameSite, SessionConfig,
+};
+pub use error_bag::ErrorBag;
 pub use handlers::Handler;
 pub use manager::Manager;
-pub use middleware::{StartSession, TokenMismatchError, VerifyCsrfToken};
-pub use session::Session;
+pub use middleware::{
+	CsrfError, RouteName, SessionError, StartSession, TokenMismatchError, VerifyCsrfToken,
+};
+pub use session::{Session, SessionKey};
+pub use sweeper::Sweeper;
 
 mod config;
+mod error_bag;
 pub mod handlers;
 mod manager;
 mod middleware;
-mod session;
+pub mod session;
+mod sweeper;
 
+/// Register the session constructors.
+///
+/// [`StartSession`] and [`VerifyCsrfToken`] are not applied here: wrap the routes that need a session
+/// with them, using [`SessionError`] and [`CsrfError`] as their error handlers. [`VerifyCsrfToken`] also
+/// needs a request-scoped [`RouteName`] constructor, as only the application knows its routes' names.
 pub fn register(bp: &mut Blueprint) {
 	bp.constructor(f!(crate::Session::new), Lifecycle::RequestScoped)
 		.cloning(CloningStrategy::CloneIfNecessary);
 
 	bp.constructor(f!(crate::Manager::new), Lifecycle::Singleton);
-	bp.wrap(f!(crate::StartSession::handle));
 }
@@ -1,16 +1,46 @@
-use crate::{handlers::FileHandler, Handler, SessionConfig};
+use std::sync::Arc;
+
+use crate::{
+	handlers::{Backend, BackendError},
+	sweeper::SweeperTask,
+	SessionConfig, Sweeper,
+};
 
 #[derive(Debug, Clone)]
 pub struct Manager {
-	config: SessionConfig,
+	backend: Backend,
+	/// The background sweeper, sharing `backend` so it sees the same sessions. Stopped when the last clone of the manager is dropped.
+	_sweeper: Option<Arc<SweeperTask>>,
 }
 
 impl Manager {
-	pub fn new(config: SessionConfig) -> Self {
-		Self { config }
+	/// Create a session manager for the configured driver.
+	/// When `sweep_interval` is set, this also starts a background [`Sweeper`] for the manager's handler.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the session configuration is invalid for the selected driver.
+	///
+	/// # Panics
+	///
+	/// Panics if `sweep_interval` is set and this is called outside of a Tokio runtime.
+	pub fn new(config: SessionConfig) -> Result<Self, BackendError> {
+		let backend = Backend::from_config(&config)?;
+		let sweeper =
+			Sweeper::new(backend.clone(), &config).map(|sweeper| Arc::new(sweeper.spawn()));
+
+		Ok(Self {
+			backend,
+			_sweeper: sweeper,
+		})
+	}
+
+	/// The handler sessions are stored with.
+	pub fn backend(&self) -> Backend {
+		self.backend.clone()
 	}
 
-	pub(crate) async fn get_backend(&self) -> impl Handler {
-		FileHandler::new(self.config.file_location.clone(), self.config.lifetime)
+	pub(crate) async fn get_backend(&self) -> Backend {
+		self.backend.clone()
 	}
 }
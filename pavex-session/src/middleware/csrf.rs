@@ -1,65 +1,207 @@
-use std::{env, future::IntoFuture};
+use std::{convert::Infallible, future::IntoFuture};
 
 use pavex::{
-	http::{HeaderName, StatusCode},
+	http::{header, header::InvalidHeaderValue, HeaderName, StatusCode},
 	middleware::Next,
-	request::RequestHead,
+	request::{body::BufferedBody, path::MatchedPathPattern, RequestHead},
 	response::{IntoResponse, Response},
 };
 
-use crate::{CsrfConfig, Session, SessionConfig};
+use super::append_cookie;
+use crate::{CsrfConfig, Origin, Session, SessionConfig};
+
+/// The cookie holding the CSRF token, read by frontend HTTP clients and sent back as `X-XSRF-TOKEN`.
+const XSRF_COOKIE: &str = "XSRF-TOKEN";
+
+/// The name of the route handling the request, if it has one, matched against `csrf.exclude_routes`.
+#[derive(Debug, Clone, Default)]
+pub struct RouteName(pub Option<String>);
 
 pub struct VerifyCsrfToken {}
 
 impl VerifyCsrfToken {
-	pub async fn handle<C: IntoFuture<Output = Response>>(
+	pub async fn handle<C: IntoFuture<Output = Response, IntoFuture: Send> + Send>(
 		req: &RequestHead,
+		route: &MatchedPathPattern,
+		route_name: &RouteName,
+		body: &BufferedBody,
 		session: &Session,
 		config: &SessionConfig,
 		next: Next<C>,
-	) -> Result<Response, TokenMismatchError> {
-		if Self::is_reading(req)
-			|| Self::running_tests()
-			|| Self::is_excluded(req, &config.csrf)
-			|| Self::tokens_match(req, session)
+	) -> Result<Response, CsrfError> {
+		if config.csrf.enabled
+			&& !Self::is_reading(req)
+			&& !Self::is_excluded(req, route, route_name, &config.csrf)
 		{
-			let response = next.into_future().await;
+			if config.csrf.verify_origin && !Self::is_same_origin(req, config) {
+				return Err(CsrfError::CrossSite);
+			}
 
-			return Ok(response);
+			if !Self::tokens_match(req, body, session).await {
+				return Err(TokenMismatchError {}.into());
+			}
 		}
 
-		Err(TokenMismatchError {})
+		let mut response = next.into_future().await;
+		Self::add_cookie_to_response(session, &mut response, config)?;
+
+		Ok(response)
 	}
 
 	fn is_reading(req: &RequestHead) -> bool {
 		req.method == "GET" || req.method == "HEAD" || req.method == "OPTIONS"
 	}
 
-	fn running_tests() -> bool {
-		let env = env::var("APP_ENV").unwrap_or_default();
+	fn is_excluded(
+		req: &RequestHead,
+		route: &MatchedPathPattern,
+		route_name: &RouteName,
+		config: &CsrfConfig,
+	) -> bool {
+		let is_excluded_path = config
+			.exclude_paths
+			.iter()
+			.any(|pattern| pattern.matches(req.target.path()) || pattern.matches(route.inner()));
+
+		is_excluded_path
+			|| route_name.0.as_deref().is_some_and(|name| {
+				config
+					.exclude_routes
+					.iter()
+					.any(|pattern| pattern.matches(name))
+			})
+	}
+
+	/// Check the request comes from our own origin or a trusted one, using `Sec-Fetch-Site`, then `Origin`, then `Referer`.
+	/// Requests carrying none of these headers (e.g. from non-browser clients) are allowed through.
+	///
+	/// Our own origin is the `Host` header, with `https` as its scheme when session cookies are `secure`.
+	fn is_same_origin(req: &RequestHead, config: &SessionConfig) -> bool {
+		let fetch_site = Self::header(req, "sec-fetch-site");
+		if matches!(fetch_site.as_deref(), Some("same-origin" | "none")) {
+			return true;
+		}
+
+		let origin = Self::header(req, "origin")
+			.filter(|origin| origin != "null")
+			.or_else(|| Self::header(req, "referer"));
+
+		let Some(origin) = origin else {
+			return fetch_site.is_none();
+		};
 
-		env == "test" || env == "testing"
+		let Some(origin) = Origin::of_url(&origin) else {
+			return false;
+		};
+
+		let scheme = if config.secure { "https" } else { "http" };
+		let own_origin = Self::header(req, "host")
+			.and_then(|host| Origin::of_url(&format!("{scheme}://{host}")));
+
+		own_origin.as_ref() == Some(&origin) || config.csrf.trusted_origins.contains(&origin)
 	}
 
-	fn is_excluded(req: &RequestHead, config: &CsrfConfig) -> bool {
-		config
-			.exclude_paths
-			.iter()
-			.any(|route| req.target.path() == route)
+	async fn tokens_match(req: &RequestHead, body: &BufferedBody, session: &Session) -> bool {
+		Self::get_token(req, body)
+			.await
+			.is_some_and(|token| session.verify_token(&token))
+	}
+
+	async fn get_token(req: &RequestHead, body: &BufferedBody) -> Option<String> {
+		if let Some(token) = Self::header(req, "x-csrf-token") {
+			return Some(token);
+		}
+
+		if let Some(token) = Self::header(req, "x-xsrf-token") {
+			return Some(token);
+		}
+
+		Self::body_token(req, body).await
 	}
 
-	fn tokens_match(req: &RequestHead, session: &Session) -> bool {
-		let token = Self::get_token(req);
+	fn header(req: &RequestHead, name: &'static str) -> Option<String> {
+		req.headers
+			.get(HeaderName::from_static(name))
+			.and_then(|value| value.to_str().ok())
+			.map(ToString::to_string)
+	}
 
-		token.is_some() && token == session.token()
+	/// Read the `_token` field from an urlencoded or multipart form body.
+	async fn body_token(req: &RequestHead, body: &BufferedBody) -> Option<String> {
+		let content_type = req.headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+		let mime = content_type.parse::<mime::Mime>().ok()?;
+
+		match (mime.type_(), mime.subtype()) {
+			(mime::APPLICATION, mime::WWW_FORM_URLENCODED) => {
+				serde_urlencoded::from_bytes::<Vec<(String, String)>>(&body.bytes)
+					.ok()?
+					.into_iter()
+					.find_map(|(key, value)| (key == "_token").then_some(value))
+			},
+			(mime::MULTIPART, mime::FORM_DATA) => {
+				let boundary = multer::parse_boundary(content_type).ok()?;
+				let bytes = body.bytes.clone();
+				let mut multipart = multer::Multipart::new(
+					futures::stream::once(async move { Ok::<_, Infallible>(bytes) }),
+					boundary,
+				);
+
+				while let Ok(Some(field)) = multipart.next_field().await {
+					if field.name() == Some("_token") {
+						return field.text().await.ok();
+					}
+				}
+
+				None
+			},
+			_ => None,
+		}
 	}
 
-	fn get_token(req: &RequestHead) -> Option<String> {
-		let Some(token) = req.headers.get(HeaderName::from_static("x-csrf-token")) else {
-			return None;
+	fn add_cookie_to_response(
+		session: &Session,
+		response: &mut Response,
+		config: &SessionConfig,
+	) -> Result<(), CsrfError> {
+		let Some(token) = session.masked_token() else {
+			return Ok(());
 		};
 
-		token.to_str().map(ToString::to_string).ok()
+		let mut cookie = config.build_cookie(XSRF_COOKIE.to_string(), token);
+		cookie.set_http_only(false);
+
+		append_cookie(response, &cookie).map_err(CsrfError::InvalidCookie)
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CsrfError {
+	#[error(transparent)]
+	TokenMismatch(#[from] TokenMismatchError),
+
+	#[error("Cross-site request rejected.")]
+	CrossSite,
+
+	#[error("failed to add the XSRF-TOKEN cookie to the response: {0}")]
+	InvalidCookie(#[source] InvalidHeaderValue),
+}
+
+impl CsrfError {
+	/// Build the response sent when a request fails CSRF verification.
+	/// This is the error handler registered for [`VerifyCsrfToken`].
+	pub fn to_response(&self) -> Response {
+		match self {
+			Self::TokenMismatch(error) => error.to_response(),
+			Self::CrossSite => Response::forbidden().set_typed_body(self.to_string()),
+			Self::InvalidCookie(_) => Response::internal_server_error()
+				.set_typed_body("The CSRF token could not be sent."),
+		}
+	}
+}
+
+impl IntoResponse for CsrfError {
+	fn into_response(self) -> Response {
+		self.to_response()
 	}
 }
 
@@ -67,8 +209,15 @@ impl VerifyCsrfToken {
 #[error("CSRF token mismatch.")]
 pub struct TokenMismatchError {}
 
+impl TokenMismatchError {
+	/// Build the response sent when the CSRF token is missing or invalid.
+	pub fn to_response(&self) -> Response {
+		Response::new(StatusCode::from_u16(419).unwrap()).set_typed_body(self.to_string())
+	}
+}
+
 impl IntoResponse for TokenMismatchError {
 	fn into_response(self) -> Response {
-		Response::new(StatusCode::from_u16(419).unwrap()).set_typed_body(self.to_string())
+		self.to_response()
 	}
 }
@@ -1,5 +1,21 @@
+use cookie::Cookie;
+use pavex::{
+	http::{header, header::InvalidHeaderValue, HeaderValue},
+	response::Response,
+};
+
 mod csrf;
 mod session;
 
-pub use csrf::{TokenMismatchError, VerifyCsrfToken};
-pub use session::StartSession;
+pub use csrf::{CsrfError, RouteName, TokenMismatchError, VerifyCsrfToken};
+pub(crate) use session::RequestHeadCookiesExt;
+pub use session::{SessionError, StartSession};
+
+/// Add a `Set-Cookie` header for the given cookie to the response.
+fn append_cookie(response: &mut Response, cookie: &Cookie<'_>) -> Result<(), InvalidHeaderValue> {
+	let value = HeaderValue::try_from(cookie.encoded().to_string())?;
+
+	response.headers_mut().append(header::SET_COOKIE, value);
+
+	Ok(())
+}
@@ -1,99 +1,191 @@
 use cookie::{Cookie, CookieJar};
 use pavex::{
-	http::{header, HeaderName, HeaderValue, Method},
+	http::{header, header::InvalidHeaderValue, HeaderName, Method},
 	middleware::Next,
 	request::RequestHead,
-	response::Response,
+	response::{IntoResponse, Response},
 };
 use rand::Rng;
-use std::future::IntoFuture;
+use std::{collections::HashMap, future::IntoFuture};
 
-use crate::{handlers, Handler, Manager, Session, SessionConfig};
+use super::append_cookie;
+use crate::{
+	config::FailureMode, handlers::BackendError, session, Handler, Manager, Session, SessionConfig,
+};
 
 pub struct StartSession {}
 
 impl StartSession {
-	pub async fn handle<C: IntoFuture<Output = Response>>(
+	pub async fn handle<C: IntoFuture<Output = Response, IntoFuture: Send> + Send>(
 		req: &RequestHead,
 		mut session: Session,
 		config: SessionConfig,
 		manager: &Manager,
 		next: Next<C>,
-	) -> Response {
+	) -> Result<Response, SessionError> {
+		// A malformed ID can't name a stored session, so it's treated like no cookie at all instead of
+		// being handed to the handler.
 		let session_id = req
 			.cookies()
 			.get(&config.cookie_name)
-			.map(|cookie| cookie.value().to_string());
+			.map(|cookie| cookie.value().to_string())
+			.filter(|id| session::is_valid_id(id));
 
 		let mut session_backend = manager.get_backend().await;
-
-		let session_contents = match session_id.as_ref() {
-			None => Default::default(),
-			Some(session_id) => session_backend.read(session_id).await.unwrap_or_default(),
+		session_backend.set_request(req);
+
+		let (session_id, session_contents) = match session_id {
+			None => (None, HashMap::new()),
+			Some(session_id) => match session_backend.read(&session_id).await {
+				Ok(contents) => (Some(session_id), contents),
+				// Start over under a new ID, so the empty session isn't written over the unreadable one.
+				Err(e) => (None, Self::recover(&config, SessionError::Read(e))?),
+			},
 		};
 
-		session.start(session_id, session_contents).unwrap();
-		dbg!(&session);
+		session.start(session_id, session_contents)?;
+		tracing::debug!(driver = ?config.driver, "Session started");
 
-		Self::collect_garbage(&mut session_backend, &config)
-			.await
-			.unwrap();
+		Self::collect_garbage(&mut session_backend, &config).await;
 
 		let mut response = next.into_future().await;
 
-		Self::store_current_url(&mut session, req).unwrap();
-		Self::add_cookie_to_response(&session, &mut response, &config);
+		Self::store_current_url(&mut session, req);
+		Self::add_cookie_to_response(&session, &mut response, &config)?;
+
+		let session_id = session.id();
+		let obsolete_id = session.take_obsolete_id();
+		let attributes = session.end()?;
+
+		// Destroy the rotated-out session first, so handlers that queue cookies don't clobber the new one.
+		if let Some(obsolete_id) = obsolete_id {
+			if let Err(e) = session_backend.destroy(&obsolete_id).await {
+				Self::recover::<()>(&config, SessionError::Write(e))?;
+			}
+		}
+
+		// Unchanged sessions only need their lifetime extended, which is much cheaper than a rewrite.
+		let result = if session.is_dirty() {
+			session_backend.write(&session_id, attributes).await
+		} else {
+			session_backend.touch(&session_id).await
+		};
+
+		if let Err(e) = result {
+			Self::recover::<()>(&config, SessionError::Write(e))?;
+		}
 
-		#[allow(clippy::unnecessary_to_owned)]
-		session_backend
-			.write(&session.id().to_string(), session.end().unwrap())
-			.await
-			.unwrap();
+		for cookie in session_backend.queued_cookies() {
+			append_cookie(&mut response, &cookie).map_err(SessionError::InvalidCookie)?;
+		}
 
-		response
+		Ok(response)
 	}
 
-	async fn collect_garbage(
-		backend: &mut impl Handler,
-		config: &SessionConfig,
-	) -> Result<(), handlers::file::Error> {
-		let mut rng = rand::thread_rng();
+	/// Decide whether a handler failure aborts the request, according to the configured failure mode.
+	fn recover<T: Default>(config: &SessionConfig, error: SessionError) -> Result<T, SessionError> {
+		match config.failure_mode {
+			FailureMode::Open => {
+				tracing::warn!(
+					error.msg = %error,
+					error.error_chain = ?error,
+					"Continuing with an empty session after a session handler failure"
+				);
+
+				Ok(T::default())
+			},
+			FailureMode::Closed => {
+				tracing::error!(
+					error.msg = %error,
+					error.error_chain = ?error,
+					"Aborting the request after a session handler failure"
+				);
+
+				Err(error)
+			},
+		}
+	}
+
+	/// Run the garbage collector if the lottery says so. Failures are logged, but never abort the request.
+	/// Skipped entirely when a background [`Sweeper`](crate::Sweeper) is configured.
+	async fn collect_garbage(backend: &mut impl Handler, config: &SessionConfig) {
+		if config.sweep_interval.is_some() {
+			return;
+		}
+
+		let hits_lottery = rand::thread_rng().gen_range(1..config.lottery[1]) <= config.lottery[0];
 
-		if rng.gen_range(1..config.lottery[1]) <= config.lottery[0] {
-			backend.collect_garbage(&config.lifetime).await.unwrap();
+		if !hits_lottery {
+			return;
 		}
 
-		Ok(())
+		match backend.collect_garbage(&config.lifetime).await {
+			Ok(expired) => tracing::debug!(expired, "Collected expired sessions"),
+			Err(e) => tracing::warn!(
+				error.msg = %e,
+				error.error_chain = ?e,
+				"Failed to collect expired sessions"
+			),
+		}
 	}
 
-	fn store_current_url(
-		session: &mut Session,
-		req: &RequestHead,
-	) -> Result<(), handlers::file::Error> {
+	/// Remember the URL of page visits, so users can be sent back to them.
+	/// Revisiting the same page leaves the session untouched, so it doesn't need to be rewritten.
+	fn store_current_url(session: &mut Session, req: &RequestHead) {
 		let is_xhr = req
 			.headers
 			.get(HeaderName::from_static("x-requested-with"))
 			.map(|v| v.as_bytes() == b"XMLHttpRequest")
 			.unwrap_or_default();
 
-		if req.method == Method::GET && !is_xhr {
-			session.set_previous_url(req.target.to_string());
+		if req.method != Method::GET || is_xhr {
+			return;
+		}
+
+		let url = req.target.to_string();
+		if session.previous_url().as_ref() != Some(&url) {
+			session.set_previous_url(url);
 		}
+	}
+
+	fn add_cookie_to_response(
+		session: &Session,
+		response: &mut Response,
+		config: &SessionConfig,
+	) -> Result<(), SessionError> {
+		let cookie = config.build_cookie(config.cookie_name.clone(), session.id());
+
+		append_cookie(response, &cookie).map_err(SessionError::InvalidCookie)
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+	#[error("failed to read the session: {0}")]
+	Read(#[source] BackendError),
+
+	#[error("failed to write the session: {0}")]
+	Write(#[source] BackendError),
 
-		Ok(())
+	#[error(transparent)]
+	Session(#[from] session::Error),
+
+	#[error("failed to add the session cookie to the response: {0}")]
+	InvalidCookie(#[source] InvalidHeaderValue),
+}
+
+impl SessionError {
+	/// Build the response sent when the session middleware fails.
+	/// This is the error handler registered for [`StartSession`].
+	pub fn to_response(&self) -> Response {
+		Response::internal_server_error()
+			.set_typed_body("The session could not be loaded or saved.")
 	}
+}
 
-	fn add_cookie_to_response(session: &Session, response: &mut Response, config: &SessionConfig) {
-		let cookie = Cookie::build((config.cookie_name.clone(), session.id().to_string()))
-			.secure(config.secure)
-			.path(config.path.clone())
-			.http_only(config.http_only)
-			.build();
-
-		response.headers_mut().append(
-			header::SET_COOKIE,
-			HeaderValue::from_str(&cookie.to_string()).unwrap(),
-		);
+impl IntoResponse for SessionError {
+	fn into_response(self) -> Response {
+		self.to_response()
 	}
 }
 
@@ -1,24 +1,48 @@
-use std::{cell::RefCell, collections::HashMap, fmt::Debug, rc::Rc};
+use std::{
+	collections::{hash_map::Entry, HashMap},
+	fmt::Debug,
+	marker::PhantomData,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard,
+	},
+};
 
-use rand::{distributions::Alphanumeric, Rng};
-use serde_json::Value;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64, Engine};
+use rand::{distributions::Alphanumeric, Rng, RngCore};
+use serde_json::{Map, Value};
+use subtle::ConstantTimeEq;
+
+use crate::ErrorBag;
+
+/// The session key validation errors are flashed under.
+const ERRORS_KEY: &str = "errors";
+
+/// The session key the previous request's input is flashed under.
+const OLD_INPUT_KEY: &str = "_old_input";
 
 #[derive(Clone, Default)]
 pub struct Session {
 	/// The session ID.
-	id: Rc<RefCell<String>>,
+	id: Arc<RwLock<String>>,
+	/// A session ID that was rotated out and should be destroyed in storage.
+	obsolete_id: Arc<RwLock<Option<String>>>,
 	/// Whether the session has been started.
-	started: Rc<RefCell<bool>>,
+	started: Arc<AtomicBool>,
+	/// Whether the session has been modified since it was started.
+	dirty: Arc<AtomicBool>,
 	/// The session attributes.
-	attributes: Rc<RefCell<HashMap<String, serde_json::Value>>>,
+	attributes: Arc<RwLock<HashMap<String, serde_json::Value>>>,
 }
 
 impl Debug for Session {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		f.debug_struct("Session")
-			.field("id", &self.id.borrow())
-			.field("started", &*self.started.borrow())
-			.field("attributes", &self.attributes.borrow())
+			.field("id", &*read(&self.id))
+			.field("obsolete_id", &*read(&self.obsolete_id))
+			.field("started", &self.has_started())
+			.field("dirty", &self.is_dirty())
+			.field("attributes", &*read(&self.attributes))
 			.finish()
 	}
 }
@@ -40,13 +64,16 @@ impl Session {
 		}
 
 		self.set_id(id);
-		self.attributes.replace(attributes);
+		*write(&self.attributes) = attributes;
+		*write(&self.obsolete_id) = None;
+
+		self.dirty.store(false, Ordering::SeqCst);
 
 		if !self.has("_token") {
 			self.regenerate_token();
 		}
 
-		*self.started.borrow_mut() = true;
+		self.started.store(true, Ordering::SeqCst);
 
 		Ok(())
 	}
@@ -54,42 +81,56 @@ impl Session {
 	/// Save the session data to storage.
 	pub fn end(&mut self) -> Result<HashMap<String, serde_json::Value>, Error> {
 		self.age_flash_data();
-		*self.started.borrow_mut() = false;
+		self.started.store(false, Ordering::SeqCst);
 
-		Ok(self.attributes.take())
+		Ok(std::mem::take(&mut *write(&self.attributes)))
 	}
 
 	/// Age the flash data for the session.
 	fn age_flash_data(&mut self) {
-		self.forget(self.get::<Vec<String>>("_flash.old").unwrap_or_default());
-
-		self.set(
-			"_flash.old",
-			self.get::<Vec<String>>("_flash.new").unwrap_or_default(),
-		);
+		let old = self.get::<Vec<String>>("_flash.old").unwrap_or_default();
+		let new = self.get::<Vec<String>>("_flash.new").unwrap_or_default();
 
+		self.forget(old);
+		self.set("_flash.old", new);
 		self.set::<Vec<&str>>("_flash.new", vec![]);
 	}
 
 	/// Get all of the session data.
+	/// Returns an empty map if the session has not been started; use [`Session::try_all`] to tell these apart.
 	pub fn all(&self) -> HashMap<String, serde_json::Value> {
-		if !self.has_started() {
-			panic!("Tried to read from session before it was started");
-		}
+		self.try_all().unwrap_or_else(|error| {
+			log_error(&error);
+			HashMap::new()
+		})
+	}
 
-		self.attributes.borrow().clone()
+	/// Get all of the session data, failing if the session has not been started.
+	pub fn try_all(&self) -> Result<HashMap<String, serde_json::Value>, Error> {
+		self.ensure_started()?;
+
+		Ok(read(&self.attributes).clone())
 	}
 
 	/// Get a subset of the session data.
+	/// Returns an empty map if the session has not been started; use [`Session::try_only`] to tell these apart.
 	pub fn only<K: IntoKey>(&self, keys: K) -> HashMap<String, serde_json::Value> {
-		if !self.has_started() {
-			panic!("Tried to read from session before it was started");
-		}
+		self.try_only(keys).unwrap_or_else(|error| {
+			log_error(&error);
+			HashMap::new()
+		})
+	}
+
+	/// Get a subset of the session data, failing if the session has not been started.
+	pub fn try_only<K: IntoKey>(
+		&self,
+		keys: K,
+	) -> Result<HashMap<String, serde_json::Value>, Error> {
+		self.ensure_started()?;
 
 		let keys = keys.get_keys();
 
-		self.attributes
-			.borrow()
+		Ok(read(&self.attributes)
 			.iter()
 			.fold(HashMap::new(), |mut acc, (key, value)| {
 				if keys.contains(key) {
@@ -97,16 +138,16 @@ impl Session {
 				}
 
 				acc
-			})
+			}))
 	}
 
 	/// Checks if a key exists.
 	pub fn exists<K: IntoKey>(&self, key: K) -> bool {
-		let attributes = self.attributes.borrow();
+		let attributes = read(&self.attributes);
 
 		key.get_keys()
 			.iter()
-			.all(|key| attributes.contains_key(key))
+			.all(|key| lookup(&attributes, key).is_some())
 	}
 
 	/// Determine if the given key is missing from the session data.
@@ -116,11 +157,11 @@ impl Session {
 
 	/// Checks if a key is present and not null.
 	pub fn has<K: IntoKey>(&self, key: K) -> bool {
-		let attributes = self.attributes.borrow();
+		let attributes = read(&self.attributes);
 		let keys = key.get_keys();
 
 		keys.iter().all(|key| {
-			let Some(value) = attributes.get(key) else {
+			let Some(value) = lookup(&attributes, key) else {
 				return false;
 			};
 
@@ -129,15 +170,37 @@ impl Session {
 	}
 
 	/// Get an item from the session.
+	/// Dotted keys reach into nested values (`cart.items.0`) when no attribute has that exact name.
+	/// Returns `None` if the session has not been started or the value is not a `T`; use [`Session::try_get`] to tell these apart.
 	pub fn get<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
-		if !self.has_started() {
-			panic!("Tried to read from session before it was started");
-		}
+		self.try_get(key).unwrap_or_else(|error| {
+			log_error(&error);
+			None
+		})
+	}
 
-		self.attributes
-			.borrow()
-			.get(key)
-			.map(|value| serde_json::from_value(value.clone()).unwrap())
+	/// Get an item from the session, failing if the session has not been started or the value is not a `T`.
+	pub fn try_get<T: serde::de::DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Error> {
+		self.ensure_started()?;
+
+		lookup(&read(&self.attributes), key)
+			.map(|value| {
+				serde_json::from_value(value.clone()).map_err(|error| Error::TypeMismatch {
+					key: key.to_string(),
+					reason: error.to_string(),
+				})
+			})
+			.transpose()
+	}
+
+	/// Get the item stored under a typed key.
+	pub fn get_typed<T: serde::de::DeserializeOwned>(&self, key: &SessionKey<T>) -> Option<T> {
+		self.get(key.name())
+	}
+
+	/// Put a value in the session under a typed key.
+	pub fn set_typed<T: serde::Serialize>(&mut self, key: &SessionKey<T>, value: T) {
+		self.set(key.name(), value);
 	}
 
 	/// Get the value of a given key and then forget it.
@@ -150,27 +213,76 @@ impl Session {
 
 	/// Replace the given session attributes entirely.
 	pub fn replace(&mut self, attributes: HashMap<String, serde_json::Value>) {
-		self.attributes.replace(attributes);
+		*write(&self.attributes) = attributes;
+		self.mark_dirty();
 	}
 
 	/// Put a key / value pair in the session.
+	/// Values that fail to serialize are logged and dropped; use [`Session::try_set`] to handle the error.
 	pub fn set<T: serde::Serialize>(&mut self, key: &str, value: T) {
-		self.attributes
-			.borrow_mut()
-			.insert(key.to_string(), serde_json::to_value(value).unwrap());
+		if let Err(error) = self.try_set(key, value) {
+			log_error(&error);
+		}
+	}
+
+	/// Put a key / value pair in the session, failing if the value cannot be serialized.
+	/// Like [`Session::get`], dotted keys write into nested values when no attribute has that exact name,
+	/// creating missing objects along the way. Fails if the path runs into a value that isn't an object or array.
+	pub fn try_set<T: serde::Serialize>(&mut self, key: &str, value: T) -> Result<(), Error> {
+		let value = serde_json::to_value(value).map_err(|error| Error::Serialization {
+			key: key.to_string(),
+			source: error,
+		})?;
+		let mut attributes = write(&self.attributes);
+
+		// An attribute with this exact name is the one `get` reads, so it's overwritten in place.
+		if let Some(previous) = attributes.get_mut(key) {
+			if *previous != value {
+				*previous = value;
+				self.mark_dirty();
+			}
+
+			return Ok(());
+		}
+
+		let Some((parent, segment)) = lookup_parent_mut(&mut attributes, key, true)? else {
+			attributes.insert(key.to_string(), value);
+			self.mark_dirty();
+
+			return Ok(());
+		};
+
+		let previous = match parent {
+			Value::Object(map) => map.insert(segment.to_string(), value.clone()),
+			Value::Array(items) => {
+				let item = segment
+					.parse::<usize>()
+					.ok()
+					.and_then(|index| items.get_mut(index))
+					.ok_or_else(|| Error::missing_index(key))?;
+
+				Some(std::mem::replace(item, value.clone()))
+			},
+			_ => return Err(Error::not_a_container(key)),
+		};
+
+		if previous.as_ref() != Some(&value) {
+			self.mark_dirty();
+		}
+
+		Ok(())
 	}
 
 	/// Get an item from the session, or store the default value.
+	/// A value of the wrong type is replaced by the default.
 	pub fn remember<T: for<'de> serde::Deserialize<'de> + serde::Serialize>(
 		&mut self,
 		key: &str,
 		value: impl FnOnce() -> T,
 	) -> T {
-		if let Some(value) = self.attributes.borrow().get(key) {
-			if !value.is_null() {
-				return serde_json::from_value(value.clone()).unwrap();
-			}
-		};
+		if let Some(value) = self.get(key) {
+			return value;
+		}
 
 		let value = value();
 		self.set(key, &value);
@@ -178,31 +290,106 @@ impl Session {
 		value
 	}
 
-	/// Push a value onto a session array.
+	/// Push a value onto a session array, creating it if the key is missing.
 	pub fn push<T: serde::Serialize>(&mut self, key: &str, value: T) {
-		let mut default_values = Vec::new();
-		let mut attributes = self.attributes.borrow_mut();
+		if let Err(error) = self.try_push(key, value) {
+			log_error(&error);
+		}
+	}
+
+	/// Push a value onto a session array, failing if the key holds something other than an array.
+	pub fn try_push<T: serde::Serialize>(&mut self, key: &str, value: T) -> Result<(), Error> {
+		let value = serde_json::to_value(value).map_err(|error| Error::Serialization {
+			key: key.to_string(),
+			source: error,
+		})?;
+
+		match write(&self.attributes).entry(key.to_string()) {
+			Entry::Occupied(entry) => match entry.into_mut() {
+				Value::Array(values) => values.push(value),
+				_ => return Err(Error::not_an_array(key)),
+			},
+			Entry::Vacant(entry) => {
+				entry.insert(Value::Array(vec![value]));
+			},
+		};
+
+		self.mark_dirty();
+
+		Ok(())
+	}
+
+	/// Remove every occurrence of a value from a session array, returning whether any was removed.
+	pub fn pull_from_array<T: serde::Serialize>(&mut self, key: &str, value: T) -> bool {
+		self.try_pull_from_array(key, value)
+			.unwrap_or_else(|error| {
+				log_error(&error);
+				false
+			})
+	}
 
+	/// Remove every occurrence of a value from a session array, failing if the key holds something other than an array.
+	pub fn try_pull_from_array<T: serde::Serialize>(
+		&mut self,
+		key: &str,
+		value: T,
+	) -> Result<bool, Error> {
+		let value = serde_json::to_value(value).map_err(|error| Error::Serialization {
+			key: key.to_string(),
+			source: error,
+		})?;
+
+		let mut attributes = write(&self.attributes);
 		let values = match attributes.get_mut(key) {
 			Some(Value::Array(values)) => values,
-			Some(_) => panic!("Key {key} is not an array"),
-			None => &mut default_values,
+			Some(_) => return Err(Error::not_an_array(key)),
+			None => return Ok(false),
 		};
 
-		values.push(serde_json::to_value(value).unwrap());
+		let len = values.len();
+		values.retain(|item| item != &value);
+
+		let removed = values.len() != len;
+		if removed {
+			self.mark_dirty();
+		}
+
+		Ok(removed)
 	}
 
 	/// Increment the value of an item in the session.
+	/// A value that is not an integer is logged and left untouched, and `0` is returned.
 	pub fn increment(&mut self, key: &str, amount: i64) -> i64 {
-		let value = match self.attributes.borrow_mut().get_mut(key) {
-			Some(Value::Number(value)) => value.as_i64().unwrap(),
-			Some(_) => panic!("Key {key} is not a number"),
-			None => 0,
-		};
+		self.try_increment(key, amount).unwrap_or_else(|error| {
+			log_error(&error);
+			0
+		})
+	}
 
-		self.set(key, value + amount);
+	/// Increment the value of an item in the session, failing if it is not an integer.
+	pub fn try_increment(&mut self, key: &str, amount: i64) -> Result<i64, Error> {
+		let value = self.try_get::<i64>(key)?.unwrap_or_default() + amount;
+		self.try_set(key, value)?;
 
-		value + amount
+		Ok(value)
+	}
+
+	/// Increment the value of an item in the session by a fractional amount.
+	/// A value that is not a number is logged and left untouched, and `0.0` is returned.
+	pub fn increment_float(&mut self, key: &str, amount: f64) -> f64 {
+		self.try_increment_float(key, amount)
+			.unwrap_or_else(|error| {
+				log_error(&error);
+				0.0
+			})
+	}
+
+	/// Increment the value of an item in the session by a fractional amount, failing if it is not a number.
+	pub fn try_increment_float(&mut self, key: &str, amount: f64) -> Result<f64, Error> {
+		let value = self.try_get::<f64>(key)?.unwrap_or_default() + amount;
+		self.try_set(key, value)?;
+
+		Ok(value)
 	}
 
 	/// Decrement the value of an item in the session.
@@ -251,28 +438,105 @@ impl Session {
 	}
 
 	/// Remove one or many items from the session.
+	/// Dotted keys remove nested values when no attribute has that exact name.
 	pub fn forget<K: IntoKey>(&mut self, keys: K) {
-		let mut attributes = self.attributes.borrow_mut();
+		let mut attributes = write(&self.attributes);
 		let keys = keys.get_keys();
 
 		for key in keys {
-			attributes.remove(&key);
+			if attributes.remove(&key).is_some() {
+				self.mark_dirty();
+				continue;
+			}
+
+			let Ok(Some((parent, segment))) = lookup_parent_mut(&mut attributes, &key, false)
+			else {
+				continue;
+			};
+
+			let removed = match parent {
+				Value::Object(map) => map.remove(segment).is_some(),
+				Value::Array(items) => segment
+					.parse::<usize>()
+					.ok()
+					.filter(|index| *index < items.len())
+					.map(|index| items.remove(index))
+					.is_some(),
+				_ => false,
+			};
+
+			if removed {
+				self.mark_dirty();
+			}
 		}
 	}
 
 	/// Remove all of the items from the session.
 	pub fn flush(&mut self) {
-		self.attributes.borrow_mut().clear();
+		let mut attributes = write(&self.attributes);
+
+		if !attributes.is_empty() {
+			attributes.clear();
+			self.mark_dirty();
+		}
 	}
 
 	/// Determine if the session has been started.
 	pub fn has_started(&self) -> bool {
-		*self.started.borrow()
+		self.started.load(Ordering::SeqCst)
+	}
+
+	/// Determine if the session has been modified since it was started.
+	pub fn is_dirty(&self) -> bool {
+		self.dirty.load(Ordering::SeqCst)
+	}
+
+	/// Fail with [`Error::NotStarted`] if the session has not been started.
+	fn ensure_started(&self) -> Result<(), Error> {
+		if self.has_started() {
+			Ok(())
+		} else {
+			Err(Error::NotStarted)
+		}
+	}
+
+	/// Flag the session as modified, so it gets written back to storage.
+	fn mark_dirty(&self) {
+		self.dirty.store(true, Ordering::SeqCst);
 	}
 
 	/// Get the current session ID.
 	pub fn id(&self) -> String {
-		self.id.borrow().clone()
+		read(&self.id).clone()
+	}
+
+	/// Flush the session data and regenerate the ID, destroying the old session.
+	pub fn invalidate(&mut self) {
+		self.flush();
+		self.migrate(true);
+	}
+
+	/// Generate a new session identifier and CSRF token.
+	/// Call this whenever the privilege level changes (e.g. on login) to prevent session fixation.
+	pub fn regenerate(&mut self, destroy: bool) {
+		self.migrate(destroy);
+		self.regenerate_token();
+	}
+
+	/// Generate a new session ID, keeping the session data.
+	/// When `destroy` is set, the session stored under the old ID is deleted at the end of the request.
+	pub fn migrate(&mut self, destroy: bool) {
+		if destroy {
+			// Only the first ID can exist in storage, later ones were never written.
+			write(&self.obsolete_id).get_or_insert_with(|| self.id());
+		}
+
+		self.set_id(None);
+	}
+
+	/// Take the session ID that should be destroyed in storage, if the session was migrated.
+	pub(crate) fn take_obsolete_id(&mut self) -> Option<String> {
+		write(&self.obsolete_id).take()
 	}
 
 	/// Get the CSRF token value.
@@ -280,6 +544,26 @@ impl Session {
 		self.get("_token")
 	}
 
+	/// Get the CSRF token masked with a fresh random pad.
+	/// Every call returns a different value, so reflecting it in compressed responses does not leak the token (BREACH).
+	pub fn masked_token(&self) -> Option<String> {
+		self.token().map(|token| mask(token.as_bytes()))
+	}
+
+	/// Check a token sent with a request against the CSRF token, in constant time.
+	/// Both the raw token and tokens from [`Session::masked_token`] are accepted.
+	pub fn verify_token(&self, candidate: &str) -> bool {
+		let Some(token) = self.token() else {
+			return false;
+		};
+
+		if candidate.len() == token.len() {
+			return candidate.as_bytes().ct_eq(token.as_bytes()).into();
+		}
+
+		unmask(candidate).is_some_and(|candidate| candidate.ct_eq(token.as_bytes()).into())
+	}
+
 	/// Regenerate the CSRF token value.
 	pub fn regenerate_token(&mut self) {
 		self.set("_token", random_str(40));
@@ -301,6 +585,40 @@ impl Session {
 		self.only(flashed)
 	}
 
+	/// Flash the request input so the form can be repopulated on the next request.
+	/// Fields whose name contains `password` are never stored.
+	pub fn with_input<T: serde::Serialize>(&mut self, input: T) {
+		let mut input = match serde_json::to_value(input) {
+			Ok(Value::Object(input)) => input,
+			Ok(_) => return,
+			Err(error) => {
+				log_error(&Error::Serialization {
+					key: OLD_INPUT_KEY.to_string(),
+					source: error,
+				});
+				return;
+			},
+		};
+
+		input.retain(|field, _| !field.contains("password"));
+		self.flash(OLD_INPUT_KEY, input);
+	}
+
+	/// Get a field from the input flashed by the previous request.
+	pub fn old<T: serde::de::DeserializeOwned>(&self, field: &str) -> Option<T> {
+		self.get(&format!("{OLD_INPUT_KEY}.{field}"))
+	}
+
+	/// Flash validation errors for the next request.
+	pub fn with_errors(&mut self, errors: ErrorBag) {
+		self.flash(ERRORS_KEY, errors);
+	}
+
+	/// Get the flashed validation errors.
+	pub fn errors(&self) -> ErrorBag {
+		self.get(ERRORS_KEY).unwrap_or_default()
+	}
+
 	/// Merge new flash keys into the new flash array.
 	fn merge_new_flashes(&mut self, keys: impl Iterator<Item = String>) {
 		let mut values = self
@@ -334,18 +652,13 @@ impl Session {
 
 	/// Set the session ID.
 	pub fn set_id(&mut self, id: Option<String>) {
-		let mut self_id = self.id.borrow_mut();
-
-		let Some(id) = id else {
-			*self_id = random_str(40);
-			return;
+		let id = match id {
+			Some(id) if is_valid_id(&id) => id,
+			_ => random_str(40),
 		};
 
-		*self_id = if id.chars().all(|c| c.is_ascii_alphanumeric()) && id.len() == 40 {
-			id
-		} else {
-			random_str(40)
-		}
+		*write(&self.id) = id;
+		self.mark_dirty();
 	}
 }
 
@@ -353,6 +666,83 @@ impl Session {
 pub enum Error {
 	#[error("This session has already been initialized")]
 	AlreadyStarted,
+
+	#[error("This session has not been started")]
+	NotStarted,
+
+	#[error("The session value for `{key}` has an unexpected type: {reason}")]
+	TypeMismatch { key: String, reason: String },
+
+	#[error("Failed to serialize the session value for `{key}`: {source}")]
+	Serialization {
+		key: String,
+		#[source]
+		source: serde_json::Error,
+	},
+}
+
+/// A session key bound to the type of the value stored under it, so every module reads it the same way.
+///
+/// ```
+/// use pavex_session::SessionKey;
+///
+/// const AUTH_USER: SessionKey<u64> = SessionKey::new("auth.user");
+/// ```
+pub struct SessionKey<T> {
+	name: &'static str,
+	_type: PhantomData<fn() -> T>,
+}
+
+impl<T> SessionKey<T> {
+	/// Create a key for values of type `T` stored under `name`.
+	pub const fn new(name: &'static str) -> Self {
+		Self {
+			name,
+			_type: PhantomData,
+		}
+	}
+
+	/// The name the value is stored under.
+	pub const fn name(&self) -> &'static str {
+		self.name
+	}
+}
+
+impl<T> Clone for SessionKey<T> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<T> Copy for SessionKey<T> {}
+
+impl<T> Debug for SessionKey<T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_tuple("SessionKey").field(&self.name).finish()
+	}
+}
+
+impl Error {
+	fn not_an_array(key: &str) -> Self {
+		Self::TypeMismatch {
+			key: key.to_string(),
+			reason: "expected an array".to_string(),
+		}
+	}
+
+	fn not_a_container(key: &str) -> Self {
+		Self::TypeMismatch {
+			key: key.to_string(),
+			reason: "the path runs into a value that is not an object or an array".to_string(),
+		}
+	}
+
+	fn missing_index(key: &str) -> Self {
+		Self::TypeMismatch {
+			key: key.to_string(),
+			reason: "the path indexes past the end of an array".to_string(),
+		}
+	}
 }
 
 pub trait IntoKey {
@@ -378,6 +768,140 @@ impl IntoKey for [&str] {
 		self.iter().map(|s| s.to_string()).collect()
 	}
 }
+impl<T> IntoKey for SessionKey<T> {
+	fn get_keys(&self) -> Vec<String> {
+		vec![self.name.to_string()]
+	}
+}
+impl<T> IntoKey for &SessionKey<T> {
+	fn get_keys(&self) -> Vec<String> {
+		vec![self.name.to_string()]
+	}
+}
+
+/// Find the value for `key`, either stored under that exact name or reached by following its dotted segments
+/// through nested objects and arrays, starting from the longest prefix that names an attribute.
+fn lookup<'a>(attributes: &'a HashMap<String, Value>, key: &str) -> Option<&'a Value> {
+	if let Some(value) = attributes.get(key) {
+		return Some(value);
+	}
+
+	key.match_indices('.').rev().find_map(|(index, _)| {
+		let value = attributes.get(&key[..index])?;
+
+		key[index + 1..]
+			.split('.')
+			.try_fold(value, |value, segment| match value {
+				Value::Object(map) => map.get(segment),
+				Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+				_ => None,
+			})
+	})
+}
+
+/// Find the value holding the last segment of a dotted `key`, following the segments before it from the
+/// longest prefix that names an attribute, the same way [`lookup`] does.
+///
+/// Returns `None` when no prefix names an attribute, so the key is treated as a flat name. With `create`,
+/// missing objects along the path are created and values that can't be traversed are an error; without
+/// it, a path that doesn't exist also returns `None`.
+fn lookup_parent_mut<'a, 'k>(
+	attributes: &'a mut HashMap<String, Value>,
+	key: &'k str,
+	create: bool,
+) -> Result<Option<(&'a mut Value, &'k str)>, Error> {
+	let Some(index) = key
+		.match_indices('.')
+		.rev()
+		.map(|(index, _)| index)
+		.find(|index| attributes.contains_key(&key[..*index]))
+	else {
+		return Ok(None);
+	};
+
+	let mut segments = key[index + 1..].split('.').collect::<Vec<_>>();
+	let last = segments.pop().unwrap_or_default();
+
+	let Some(mut value) = attributes.get_mut(&key[..index]) else {
+		return Ok(None);
+	};
+
+	for segment in segments {
+		value = match value {
+			Value::Object(map) if create => map
+				.entry(segment)
+				.or_insert_with(|| Value::Object(Map::new())),
+			Value::Object(map) => match map.get_mut(segment) {
+				Some(value) => value,
+				None => return Ok(None),
+			},
+			Value::Array(items) => match segment
+				.parse::<usize>()
+				.ok()
+				.and_then(|index| items.get_mut(index))
+			{
+				Some(value) => value,
+				None if create => return Err(Error::missing_index(key)),
+				None => return Ok(None),
+			},
+			_ if create => return Err(Error::not_a_container(key)),
+			_ => return Ok(None),
+		};
+	}
+
+	Ok(Some((value, last)))
+}
+
+/// Log a failed session operation that the caller chose not to handle.
+fn log_error(error: &Error) {
+	tracing::warn!(
+		error.msg = %error,
+		error.error_chain = ?error,
+		"Ignoring a failed session operation"
+	);
+}
+
+/// Acquire a read lock, recovering the data if another thread panicked while holding it.
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+	lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Acquire a write lock, recovering the data if another thread panicked while holding it.
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+	lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Encode `token` as a random pad followed by the token XOR-ed with that pad.
+fn mask(token: &[u8]) -> String {
+	let mut pad = vec![0; token.len()];
+	rand::thread_rng().fill_bytes(&mut pad);
+
+	let masked = pad.iter().zip(token).map(|(pad, byte)| pad ^ byte);
+
+	BASE64.encode(pad.iter().copied().chain(masked).collect::<Vec<_>>())
+}
+
+/// Recover the token from a value produced by [`mask`].
+fn unmask(masked: &str) -> Option<Vec<u8>> {
+	let bytes = BASE64.decode(masked).ok()?;
+	if bytes.is_empty() || bytes.len() % 2 != 0 {
+		return None;
+	}
+
+	let (pad, masked) = bytes.split_at(bytes.len() / 2);
+
+	Some(
+		pad.iter()
+			.zip(masked)
+			.map(|(pad, byte)| pad ^ byte)
+			.collect(),
+	)
+}
+
+/// Check a string has the shape of the session IDs we hand out: 40 alphanumeric characters.
+pub(crate) fn is_valid_id(id: &str) -> bool {
+	id.len() == 40 && id.chars().all(|c| c.is_ascii_alphanumeric())
+}
 
 /// Generate a random alpha-numeric string of the given length.
 fn random_str(len: usize) -> String {
@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+use crate::{
+	handlers::{Backend, BackendError},
+	Handler, SessionConfig,
+};
+
+/// Periodically purges expired sessions, so garbage collection never runs inside a user request.
+///
+/// The [`Manager`](crate::Manager) runs one in the background when `sweep_interval` is set.
+#[derive(Debug)]
+pub struct Sweeper {
+	backend: Backend,
+	lifetime: Duration,
+	interval: Duration,
+}
+
+impl Sweeper {
+	/// Create a sweeper purging the sessions stored by the given handler.
+	/// Returns `None` when `sweep_interval` is unset, as garbage collection then runs on the request lottery.
+	pub fn new(backend: Backend, config: &SessionConfig) -> Option<Self> {
+		let interval = config.sweep_interval?;
+
+		Some(Self {
+			backend,
+			interval,
+			lifetime: config.lifetime,
+		})
+	}
+
+	/// Purge expired sessions once, returning how many were removed.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the session handler fails to collect garbage.
+	pub async fn sweep(&mut self) -> Result<u64, BackendError> {
+		let purged = self.backend.collect_garbage(&self.lifetime).await?;
+
+		if purged == 0 {
+			tracing::debug!(sessions.purged = purged, "Swept expired sessions");
+		} else {
+			tracing::info!(sessions.purged = purged, "Swept expired sessions");
+		}
+
+		Ok(purged)
+	}
+
+	/// Run the sweeper on the current Tokio runtime until the returned task is dropped.
+	///
+	/// # Panics
+	///
+	/// Panics if called outside of a Tokio runtime.
+	pub(crate) fn spawn(self) -> SweeperTask {
+		SweeperTask(tokio::spawn(self.run()))
+	}
+
+	/// Sweep every `interval`, forever. Failures are logged and retried on the next tick.
+	pub async fn run(mut self) {
+		let mut interval = tokio::time::interval(self.interval);
+		interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+		loop {
+			interval.tick().await;
+
+			if let Err(e) = self.sweep().await {
+				tracing::warn!(
+					error.msg = %e,
+					error.error_chain = ?e,
+					"Failed to sweep expired sessions"
+				);
+			}
+		}
+	}
+}
+
+/// A sweeper running in the background, stopped when this is dropped.
+#[derive(Debug)]
+pub(crate) struct SweeperTask(JoinHandle<()>);
+
+impl Drop for SweeperTask {
+	fn drop(&mut self) {
+		self.0.abort();
+	}
+}
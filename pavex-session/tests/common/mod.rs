@@ -0,0 +1,30 @@
+use pavex_session::SessionConfig;
+use serde_json::{json, Value};
+
+/// A session config with the given top-level keys replacing the defaults below.
+pub fn try_config(overrides: Value) -> serde_json::Result<SessionConfig> {
+	let mut config = json!({
+		"driver": "memory",
+		"lifetime": "2h",
+		"file_location": std::env::temp_dir(),
+		"cookie_name": "pavex_session",
+		"path": "/",
+		"domain": null,
+		"secure": true,
+		"http_only": true,
+		"lottery": [2, 100],
+		"same_site": "lax",
+		"csrf": { "exclude_paths": [] },
+	});
+
+	if let (Some(config), Value::Object(overrides)) = (config.as_object_mut(), overrides) {
+		config.extend(overrides);
+	}
+
+	serde_json::from_value(config)
+}
+
+/// Like [`try_config`], for overrides that are known to be valid.
+pub fn config(overrides: Value) -> SessionConfig {
+	try_config(overrides).unwrap()
+}
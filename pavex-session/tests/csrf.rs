@@ -0,0 +1,242 @@
+use std::{collections::HashMap, future};
+
+use pavex::{
+	http::{header, HeaderMap, HeaderValue, Method, Version},
+	middleware::Next,
+	request::{body::BufferedBody, path::MatchedPathPattern, RequestHead},
+	response::Response,
+};
+use pavex_session::{
+	CsrfConfig, CsrfError, NamePattern, Origin, PathPattern, RouteName, Session, SessionConfig,
+	VerifyCsrfToken,
+};
+use serde_json::json;
+
+mod common;
+
+#[test]
+fn exclusion_patterns_match_single_and_nested_segments() {
+	let pattern = PathPattern::new("/webhooks/*").unwrap();
+	assert!(pattern.matches("/webhooks/stripe"));
+	assert!(!pattern.matches("/webhooks/stripe/events"));
+	assert!(!pattern.matches("/login"));
+
+	let pattern = PathPattern::new("/api/**").unwrap();
+	assert!(pattern.matches("/api/users/1/posts"));
+
+	let pattern = PathPattern::new("/users/:id").unwrap();
+	assert!(pattern.matches("/users/:id"));
+	assert!(!pattern.matches("/users/1"));
+}
+
+#[test]
+fn route_name_patterns_match_across_dots() {
+	let pattern = NamePattern::new("webhooks.*").unwrap();
+	assert!(pattern.matches("webhooks.stripe"));
+	assert!(pattern.matches("webhooks.stripe.events"));
+	assert!(!pattern.matches("auth.login"));
+}
+
+#[test]
+fn invalid_exclusion_patterns_are_rejected_on_load() {
+	let config = serde_json::from_value::<CsrfConfig>(json!({
+		"exclude_paths": ["/webhooks/[stripe"],
+	}));
+	assert!(config.is_err());
+
+	let config = serde_json::from_value::<CsrfConfig>(json!({
+		"exclude_paths": [],
+		"exclude_routes": ["webhooks.[stripe"],
+	}));
+	assert!(config.is_err());
+}
+
+#[test]
+fn trusted_origins_are_validated_on_load() {
+	for origin in [
+		"app.example.com",
+		"https://",
+		"https://app.example.com/login",
+		"https://user@app.example.com",
+	] {
+		let config = serde_json::from_value::<CsrfConfig>(json!({
+			"exclude_paths": [],
+			"trusted_origins": [origin],
+		}));
+		assert!(config.is_err(), "{origin:?} is rejected");
+	}
+
+	assert_eq!(
+		Origin::new("HTTPS://App.Example.com:443/").unwrap(),
+		Origin::new("https://app.example.com").unwrap()
+	);
+	assert_ne!(
+		Origin::new("http://app.example.com").unwrap(),
+		Origin::new("https://app.example.com").unwrap()
+	);
+}
+
+fn session() -> Session {
+	let mut session = Session::new();
+	session.start(None, HashMap::new()).unwrap();
+
+	session
+}
+
+async fn submit(
+	session: &Session,
+	headers: &[(&'static str, &str)],
+	body: String,
+) -> Result<Response, CsrfError> {
+	let config = common::config(json!({}));
+
+	submit_to(&RouteName(None), &config, session, headers, body).await
+}
+
+async fn submit_to(
+	route_name: &RouteName,
+	config: &SessionConfig,
+	session: &Session,
+	headers: &[(&'static str, &str)],
+	body: String,
+) -> Result<Response, CsrfError> {
+	let mut map = HeaderMap::new();
+	for (name, value) in headers {
+		map.append(*name, HeaderValue::from_str(value).unwrap());
+	}
+
+	let req = RequestHead {
+		headers: map,
+		method: Method::POST,
+		target: "/login".parse().unwrap(),
+		version: Version::HTTP_11,
+	};
+
+	VerifyCsrfToken::handle(
+		&req,
+		&MatchedPathPattern::new("/login"),
+		route_name,
+		&BufferedBody { bytes: body.into() },
+		session,
+		config,
+		Next::new(future::ready(Response::ok())),
+	)
+	.await
+}
+
+#[tokio::test]
+async fn requests_without_a_token_are_rejected() {
+	let session = session();
+
+	let result = submit(&session, &[], String::new()).await;
+	assert!(matches!(result, Err(CsrfError::TokenMismatch(_))));
+
+	let result = submit(
+		&session,
+		&[("x-csrf-token", "not-the-token")],
+		String::new(),
+	)
+	.await;
+	assert!(matches!(result, Err(CsrfError::TokenMismatch(_))));
+}
+
+#[tokio::test]
+async fn the_masked_token_is_accepted_from_the_xsrf_header() {
+	let session = session();
+	let token = session.masked_token().unwrap();
+
+	let response = submit(&session, &[("x-xsrf-token", &token)], String::new())
+		.await
+		.unwrap();
+
+	let cookie = response.headers().get(header::SET_COOKIE).unwrap();
+	assert!(cookie.to_str().unwrap().starts_with("XSRF-TOKEN="));
+}
+
+#[tokio::test]
+async fn the_token_is_read_from_urlencoded_forms() {
+	let session = session();
+	let body = format!(
+		"email=miguel%40example.com&_token={}",
+		session.masked_token().unwrap()
+	);
+
+	let result = submit(
+		&session,
+		&[("content-type", "application/x-www-form-urlencoded")],
+		body,
+	)
+	.await;
+
+	assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn the_token_is_read_from_multipart_forms() {
+	let session = session();
+	let body = format!(
+		"--boundary\r\nContent-Disposition: form-data; name=\"email\"\r\n\r\nmiguel@example.com\r\n\
+		 --boundary\r\nContent-Disposition: form-data; name=\"_token\"\r\n\r\n{}\r\n--boundary--\r\n",
+		session.token().unwrap()
+	);
+
+	let result = submit(
+		&session,
+		&[("content-type", "multipart/form-data; boundary=boundary")],
+		body,
+	)
+	.await;
+
+	assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn routes_excluded_by_name_skip_verification() {
+	let session = session();
+	let mut config = common::config(json!({}));
+	config.csrf.exclude_routes = vec![NamePattern::new("webhooks.*").unwrap()];
+
+	let webhook = RouteName(Some("webhooks.stripe".to_string()));
+	let result = submit_to(&webhook, &config, &session, &[], String::new()).await;
+	assert!(result.is_ok());
+
+	let login = RouteName(Some("auth.login.store".to_string()));
+	let result = submit_to(&login, &config, &session, &[], String::new()).await;
+	assert!(matches!(result, Err(CsrfError::TokenMismatch(_))));
+
+	let result = submit_to(&RouteName(None), &config, &session, &[], String::new()).await;
+	assert!(matches!(result, Err(CsrfError::TokenMismatch(_))));
+}
+
+#[tokio::test]
+async fn origins_are_compared_by_scheme_and_host() {
+	let session = session();
+	let token = session.token().unwrap();
+	let mut config = common::config(json!({}));
+	config.csrf.verify_origin = true;
+	config.csrf.trusted_origins = vec![Origin::new("https://app.example.com").unwrap()];
+
+	for (origin, is_allowed) in [
+		("https://example.com", true),
+		("http://example.com", false),
+		("https://app.example.com", true),
+		("http://app.example.com", false),
+		("https://evil.example.com", false),
+	] {
+		let headers = [
+			("host", "example.com"),
+			("origin", origin),
+			("x-csrf-token", token.as_str()),
+		];
+		let result = submit_to(&RouteName(None), &config, &session, &headers, String::new()).await;
+
+		if is_allowed {
+			assert!(result.is_ok(), "{origin} is allowed");
+		} else {
+			assert!(
+				matches!(result, Err(CsrfError::CrossSite)),
+				"{origin} is rejected"
+			);
+		}
+	}
+}
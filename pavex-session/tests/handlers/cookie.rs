@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use futures::executor::block_on;
+use pavex::{
+	http::{header, HeaderMap, HeaderValue, Method, Version},
+	request::RequestHead,
+};
+use pavex_session::{
+	handlers::{cookie::Error, CookieHandler},
+	Handler, SameSite, SessionConfig,
+};
+use serde_json::json;
+
+use crate::common;
+
+const OLD_KEY: &str = "base64:b2xkLWtleS1vbGQta2V5LW9sZC1rZXktb2xkLWtleS0=";
+const NEW_KEY: &str = "base64:bmV3LWtleS1uZXcta2V5LW5ldy1rZXktbmV3LWtleS0=";
+
+fn config(key: &str, previous_keys: &[&str]) -> SessionConfig {
+	common::config(json!({
+		"driver": "cookie",
+		"key": key,
+		"previous_keys": previous_keys,
+	}))
+}
+
+fn request_with_cookies(handler: &mut CookieHandler) -> RequestHead {
+	let cookies = handler
+		.queued_cookies()
+		.iter()
+		.map(|cookie| cookie.stripped().encoded().to_string())
+		.collect::<Vec<_>>()
+		.join("; ");
+
+	let mut headers = HeaderMap::new();
+	headers.insert(header::COOKIE, HeaderValue::from_str(&cookies).unwrap());
+
+	RequestHead {
+		headers,
+		method: Method::GET,
+		target: "/".parse().unwrap(),
+		version: Version::HTTP_11,
+	}
+}
+
+#[test]
+fn cookie_handler_reads_cookies_encrypted_with_a_previous_key() {
+	let mut old_handler = CookieHandler::new(&config(OLD_KEY, &[])).unwrap();
+	let mut new_handler = CookieHandler::new(&config(NEW_KEY, &[OLD_KEY])).unwrap();
+	let mut other_handler = CookieHandler::new(&config(NEW_KEY, &[])).unwrap();
+
+	block_on(async {
+		old_handler
+			.write("session-id", HashMap::from([("name", json!("Miguel"))]))
+			.await
+			.unwrap();
+
+		let request = request_with_cookies(&mut old_handler);
+		new_handler.set_request(&request);
+		other_handler.set_request(&request);
+
+		let attributes = new_handler.read("session-id").await.unwrap();
+		assert_eq!(attributes.get("name"), Some(&json!("Miguel")));
+
+		assert!(new_handler.read("another-id").await.unwrap().is_empty());
+		assert!(other_handler.read("session-id").await.unwrap().is_empty());
+	});
+}
+
+#[test]
+fn cookie_handler_rejects_payloads_over_the_cookie_limit() {
+	let mut handler = CookieHandler::new(&config(NEW_KEY, &[])).unwrap();
+	let value = "a".repeat(5000);
+
+	let result = block_on(handler.write("session-id", HashMap::from([("value", value)])));
+
+	assert!(matches!(result, Err(Error::TooLarge { .. })));
+	assert!(handler.queued_cookies().is_empty());
+}
+
+#[test]
+fn cookie_handler_requires_a_valid_key() {
+	let mut missing_key = config(NEW_KEY, &[]);
+	missing_key.key = None;
+
+	assert!(matches!(
+		CookieHandler::new(&missing_key),
+		Err(Error::MissingKey)
+	));
+	assert!(matches!(
+		CookieHandler::new(&config("base64:c2hvcnQ=", &[])),
+		Err(Error::InvalidKey(_))
+	));
+}
+
+#[test]
+fn the_payload_cookie_carries_the_configured_attributes() {
+	let mut config = config(NEW_KEY, &[]);
+	config.domain = Some("example.com".to_string());
+	config.same_site = SameSite::Strict;
+
+	let mut handler = CookieHandler::new(&config).unwrap();
+	block_on(handler.write("session-id", json!({}))).unwrap();
+	let cookie = handler.queued_cookies().remove(0);
+
+	assert_eq!(cookie.same_site(), Some(cookie::SameSite::Strict));
+	assert_eq!(cookie.domain(), Some("example.com"));
+	assert_eq!(
+		cookie.max_age(),
+		Some(cookie::time::Duration::seconds(2 * 60 * 60))
+	);
+
+	config.expire_on_close = true;
+	let mut handler = CookieHandler::new(&config).unwrap();
+	block_on(handler.write("session-id", json!({}))).unwrap();
+
+	assert_eq!(handler.queued_cookies()[0].max_age(), None);
+}
+
+#[test]
+fn same_site_none_requires_secure_cookies() {
+	let config = |secure: bool| {
+		common::try_config(json!({
+			"driver": "cookie",
+			"secure": secure,
+			"same_site": "none",
+			"key": NEW_KEY,
+		}))
+	};
+
+	assert!(config(true).is_ok());
+	assert!(config(false)
+		.unwrap_err()
+		.to_string()
+		.contains("`same_site: none` requires `secure`"));
+}
@@ -0,0 +1,67 @@
+use std::{net::IpAddr, time::Duration};
+
+use pavex::{
+	http::{HeaderMap, HeaderValue, Method, Version},
+	request::RequestHead,
+};
+use pavex_session::{handlers::DatabaseHandler, Handler};
+
+fn request(headers: &[(&'static str, &str)]) -> RequestHead {
+	let mut map = HeaderMap::new();
+	for (name, value) in headers {
+		map.append(*name, HeaderValue::from_str(value).unwrap());
+	}
+
+	RequestHead {
+		headers: map,
+		method: Method::GET,
+		target: "/".parse().unwrap(),
+		version: Version::HTTP_11,
+	}
+}
+
+fn handler(trusted_proxies: &[&str]) -> DatabaseHandler {
+	DatabaseHandler::new(
+		Duration::from_secs(60),
+		trusted_proxies
+			.iter()
+			.map(|ip| ip.parse::<IpAddr>().unwrap())
+			.collect(),
+	)
+}
+
+#[test]
+fn forwarded_headers_are_ignored_without_trusted_proxies() {
+	let mut handler = handler(&[]);
+	handler.set_request(&request(&[
+		("x-forwarded-for", "203.0.113.7"),
+		("x-real-ip", "203.0.113.7"),
+		("user-agent", "curl/8.0"),
+	]));
+
+	assert_eq!(handler.ip_address(), None);
+	assert_eq!(handler.user_agent(), Some("curl/8.0"));
+}
+
+#[test]
+fn the_client_is_the_last_address_not_added_by_a_trusted_proxy() {
+	let mut handler = handler(&["10.0.0.1", "10.0.0.2"]);
+
+	handler.set_request(&request(&[(
+		"x-forwarded-for",
+		"198.51.100.1, 203.0.113.7, 10.0.0.2",
+	)]));
+	assert_eq!(handler.ip_address(), Some("203.0.113.7"));
+
+	handler.set_request(&request(&[
+		("x-forwarded-for", "198.51.100.1"),
+		("x-forwarded-for", "203.0.113.7, 10.0.0.1"),
+	]));
+	assert_eq!(handler.ip_address(), Some("203.0.113.7"));
+
+	handler.set_request(&request(&[("x-forwarded-for", "not-an-ip, 10.0.0.1")]));
+	assert_eq!(handler.ip_address(), None);
+
+	handler.set_request(&request(&[("x-real-ip", "203.0.113.7")]));
+	assert_eq!(handler.ip_address(), Some("203.0.113.7"));
+}
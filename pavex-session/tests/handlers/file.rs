@@ -0,0 +1,176 @@
+use std::{
+	collections::HashMap,
+	fs,
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime},
+};
+
+use pavex_session::{handlers::FileHandler, Handler};
+use serde_json::json;
+
+fn session_dir(test: &str) -> PathBuf {
+	let path = std::env::temp_dir().join(format!("pavex-session-{}-{test}", std::process::id()));
+	let _ = fs::remove_dir_all(&path);
+
+	path
+}
+
+fn files(path: &Path) -> Vec<String> {
+	let mut files = fs::read_dir(path)
+		.unwrap()
+		.map(|entry| entry.unwrap().file_name().into_string().unwrap())
+		.collect::<Vec<_>>();
+	files.sort();
+
+	files
+}
+
+/// Make a file look like it was last modified an hour ago.
+fn backdate(path: &Path) {
+	fs::File::options()
+		.write(true)
+		.open(path)
+		.and_then(|file| file.set_modified(SystemTime::now() - Duration::from_secs(60 * 60)))
+		.unwrap();
+}
+
+#[tokio::test]
+async fn file_handler_writes_atomically_and_discards_corrupt_files() {
+	let path = session_dir("atomic");
+	let mut handler = FileHandler::new(path.clone(), Duration::from_secs(60)).unwrap();
+
+	handler
+		.write("session", HashMap::from([("name", json!("Miguel"))]))
+		.await
+		.unwrap();
+	let attributes = handler.read("session").await.unwrap();
+	assert_eq!(attributes.get("name"), Some(&json!("Miguel")));
+
+	assert_eq!(
+		files(&path),
+		["session", "session.lock"],
+		"no temporary files are left behind"
+	);
+
+	fs::write(path.join("session"), b"{\"name\": ").unwrap();
+	assert!(handler.read("session").await.unwrap().is_empty());
+
+	handler.destroy("session").await.unwrap();
+	assert!(
+		files(&path).is_empty(),
+		"the lock file goes with the session"
+	);
+
+	fs::remove_dir_all(&path).unwrap();
+}
+
+#[tokio::test]
+async fn file_handler_collects_sessions_and_leftover_files() {
+	let path = session_dir("garbage");
+	let mut handler = FileHandler::new(path.clone(), Duration::from_secs(60)).unwrap();
+
+	for id in ["expired", "alive"] {
+		handler
+			.write(id, HashMap::<String, serde_json::Value>::new())
+			.await
+			.unwrap();
+	}
+	fs::write(path.join("crashed.abcdefgh.tmp"), b"{}").unwrap();
+	fs::write(path.join("gone.lock"), b"").unwrap();
+	for file in [
+		"expired",
+		"expired.lock",
+		"alive.lock",
+		"crashed.abcdefgh.tmp",
+		"gone.lock",
+	] {
+		backdate(&path.join(file));
+	}
+
+	let collected = handler
+		.collect_garbage(&Duration::from_secs(60))
+		.await
+		.unwrap();
+	assert_eq!(collected, 1);
+	assert_eq!(files(&path), ["alive", "alive.lock"]);
+
+	fs::remove_dir_all(&path).unwrap();
+}
+
+#[tokio::test]
+async fn file_handler_rejects_ids_that_are_not_alphanumeric() {
+	let path = session_dir("ids");
+	let mut handler = FileHandler::new(path.join("sessions"), Duration::from_secs(60)).unwrap();
+
+	for id in ["", "../escaped", ".lock", "nested/session"] {
+		assert!(handler.read(id).await.is_err(), "{id:?} is rejected");
+		assert!(handler
+			.write(id, HashMap::<String, serde_json::Value>::new())
+			.await
+			.is_err());
+		assert!(handler.destroy(id).await.is_err());
+	}
+	assert!(!path.join("escaped").exists());
+
+	fs::remove_dir_all(&path).unwrap();
+}
+
+#[tokio::test]
+async fn file_handler_skips_sessions_in_use_when_collecting_garbage() {
+	let path = session_dir("in-use");
+	let mut handler = FileHandler::new(path.clone(), Duration::from_secs(60)).unwrap();
+	handler
+		.write("session", HashMap::<String, serde_json::Value>::new())
+		.await
+		.unwrap();
+	backdate(&path.join("session"));
+
+	let mut request = handler.clone();
+	request.read("session").await.unwrap();
+	let collected = handler
+		.collect_garbage(&Duration::from_secs(60))
+		.await
+		.unwrap();
+	assert_eq!(collected, 0, "the session is locked by the request");
+
+	request.touch("session").await.unwrap();
+	assert!(path.join("session").exists());
+
+	fs::remove_dir_all(&path).unwrap();
+}
+
+#[tokio::test]
+async fn file_handler_serializes_requests_for_the_same_session() {
+	let path = session_dir("serialized");
+	let handler = FileHandler::new(path.clone(), Duration::from_secs(60)).unwrap();
+
+	async fn increment(mut handler: FileHandler) {
+		let attributes = handler.read("counter").await.unwrap();
+		let count = attributes.get("count").and_then(serde_json::Value::as_u64);
+		tokio::task::yield_now().await;
+
+		handler
+			.write(
+				"counter",
+				HashMap::from([("count", json!(count.unwrap_or_default() + 1))]),
+			)
+			.await
+			.unwrap();
+	}
+
+	let requests = (0..8)
+		.map(|_| tokio::spawn(increment(handler.clone())))
+		.collect::<Vec<_>>();
+	for request in requests {
+		request.await.unwrap();
+	}
+
+	let attributes = handler.clone().read("counter").await.unwrap();
+	assert_eq!(
+		attributes.get("count"),
+		Some(&json!(8)),
+		"no update is lost"
+	);
+
+	fs::remove_dir_all(&path).unwrap();
+}
@@ -0,0 +1,8 @@
+#[path = "../common/mod.rs"]
+mod common;
+
+mod cookie;
+mod database;
+mod file;
+mod memory;
+mod redis;
@@ -0,0 +1,65 @@
+use std::{collections::HashMap, thread, time::Duration};
+
+use futures::executor::block_on;
+use pavex_session::{handlers::MemoryHandler, Handler};
+use serde_json::json;
+
+#[test]
+fn memory_handler_stores_and_destroys_sessions() {
+	let mut handler = MemoryHandler::new(Duration::from_secs(60));
+
+	block_on(async {
+		handler
+			.write("round-trip", HashMap::from([("name", json!("Miguel"))]))
+			.await
+			.unwrap();
+
+		let attributes = handler.clone().read("round-trip").await.unwrap();
+		assert_eq!(attributes.get("name"), Some(&json!("Miguel")));
+
+		handler.destroy("round-trip").await.unwrap();
+		assert!(handler.read("round-trip").await.unwrap().is_empty());
+	});
+}
+
+#[test]
+fn memory_handlers_do_not_share_sessions() {
+	let mut handler = MemoryHandler::new(Duration::from_secs(60));
+	let mut other = MemoryHandler::new(Duration::from_secs(60));
+
+	block_on(async {
+		handler
+			.write("session", HashMap::from([("name", json!("Miguel"))]))
+			.await
+			.unwrap();
+
+		assert!(other.read("session").await.unwrap().is_empty());
+	});
+}
+
+#[test]
+fn memory_handler_expires_and_collects_sessions() {
+	let mut handler = MemoryHandler::new(Duration::from_millis(50));
+
+	block_on(async {
+		handler
+			.write("expired", HashMap::from([("name", json!("Miguel"))]))
+			.await
+			.unwrap();
+		handler
+			.write("collected", HashMap::from([("name", json!("Miguel"))]))
+			.await
+			.unwrap();
+
+		thread::sleep(Duration::from_millis(100));
+
+		assert!(handler.read("expired").await.unwrap().is_empty());
+		assert_eq!(
+			handler
+				.collect_garbage(&Duration::from_millis(50))
+				.await
+				.unwrap(),
+			1
+		);
+	});
+}
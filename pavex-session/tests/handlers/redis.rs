@@ -0,0 +1,203 @@
+use std::{
+	collections::HashMap,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, Mutex,
+	},
+	time::{Duration, Instant},
+};
+
+use pavex_session::{handlers::RedisHandler, Handler, RedisConfig};
+use serde_json::json;
+use tokio::{
+	io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+	net::{TcpListener, TcpStream},
+};
+
+type Store = Arc<Mutex<HashMap<String, (String, Instant)>>>;
+
+/// A tiny in-process server that speaks enough of the Redis protocol for the session handler.
+struct StandInServer {
+	url: String,
+	store: Store,
+	/// When set, the next command is answered by closing the connection.
+	drop_connection: Arc<AtomicBool>,
+}
+
+impl StandInServer {
+	async fn spawn() -> Self {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = format!("redis://{}", listener.local_addr().unwrap());
+		let store = Store::default();
+		let drop_connection = Arc::new(AtomicBool::new(false));
+
+		let server_store = store.clone();
+		let server_drop_connection = drop_connection.clone();
+		tokio::spawn(async move {
+			loop {
+				let (stream, _) = listener.accept().await.unwrap();
+				tokio::spawn(Self::serve(
+					stream,
+					server_store.clone(),
+					server_drop_connection.clone(),
+				));
+			}
+		});
+
+		Self {
+			url,
+			store,
+			drop_connection,
+		}
+	}
+
+	async fn serve(stream: TcpStream, store: Store, drop_connection: Arc<AtomicBool>) {
+		let (reader, mut writer) = stream.into_split();
+		let mut reader = BufReader::new(reader);
+
+		while let Some(command) = Self::read_command(&mut reader).await {
+			if drop_connection.swap(false, Ordering::SeqCst) {
+				return;
+			}
+
+			let reply = Self::execute(&command, &store);
+			writer.write_all(reply.as_bytes()).await.unwrap();
+		}
+	}
+
+	async fn read_command(
+		reader: &mut BufReader<impl AsyncReadExt + Unpin>,
+	) -> Option<Vec<String>> {
+		let mut line = String::new();
+		if reader.read_line(&mut line).await.ok()? == 0 {
+			return None;
+		}
+
+		let arguments = line.trim_end().strip_prefix('*')?.parse::<usize>().ok()?;
+		let mut command = Vec::with_capacity(arguments);
+
+		for _ in 0..arguments {
+			line.clear();
+			reader.read_line(&mut line).await.ok()?;
+			let length = line.trim_end().strip_prefix('$')?.parse::<usize>().ok()?;
+
+			let mut argument = vec![0; length + 2];
+			reader.read_exact(&mut argument).await.ok()?;
+			argument.truncate(length);
+
+			command.push(String::from_utf8(argument).ok()?);
+		}
+
+		Some(command)
+	}
+
+	fn execute(command: &[String], store: &Store) -> String {
+		let mut store = store.lock().unwrap();
+		store.retain(|_, (_, expires_at)| *expires_at > Instant::now());
+
+		match command[0].to_uppercase().as_str() {
+			"GET" => store.get(&command[1]).map_or_else(
+				|| "$-1\r\n".to_string(),
+				|(value, _)| format!("${}\r\n{value}\r\n", value.len()),
+			),
+			"SETEX" => {
+				let ttl = Duration::from_secs(command[2].parse().unwrap());
+				store.insert(
+					command[1].clone(),
+					(command[3].clone(), Instant::now() + ttl),
+				);
+
+				"+OK\r\n".to_string()
+			},
+			"EXPIRE" => match store.get_mut(&command[1]) {
+				Some((_, expires_at)) => {
+					*expires_at = Instant::now() + Duration::from_secs(command[2].parse().unwrap());
+					":1\r\n".to_string()
+				},
+				None => ":0\r\n".to_string(),
+			},
+			"DEL" => format!(":{}\r\n", u8::from(store.remove(&command[1]).is_some())),
+			_ => "+OK\r\n".to_string(),
+		}
+	}
+
+	fn ttl(&self, key: &str) -> Option<Duration> {
+		let store = self.store.lock().unwrap();
+
+		store
+			.get(key)
+			.map(|(_, expires_at)| expires_at.saturating_duration_since(Instant::now()))
+	}
+}
+
+#[tokio::test]
+async fn redis_handler_stores_sessions_with_native_ttls() {
+	let server = StandInServer::spawn().await;
+	let config = RedisConfig {
+		url: server.url.clone(),
+		prefix: "sessions:".to_string(),
+	};
+	let mut handler = RedisHandler::new(&config, Duration::from_secs(60)).unwrap();
+
+	handler
+		.write("session-id", HashMap::from([("name", json!("Miguel"))]))
+		.await
+		.unwrap();
+
+	let attributes = handler.read("session-id").await.unwrap();
+	assert_eq!(attributes.get("name"), Some(&json!("Miguel")));
+
+	let ttl = server.ttl("sessions:session-id").unwrap();
+	assert!(ttl > Duration::from_secs(55) && ttl <= Duration::from_secs(60));
+
+	assert_eq!(
+		handler
+			.collect_garbage(&Duration::from_secs(60))
+			.await
+			.unwrap(),
+		0
+	);
+
+	handler.destroy("session-id").await.unwrap();
+	assert!(handler.read("session-id").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn redis_handler_lets_the_server_expire_sessions() {
+	let server = StandInServer::spawn().await;
+	let config = RedisConfig {
+		url: server.url.clone(),
+		prefix: "sessions:".to_string(),
+	};
+	let mut handler = RedisHandler::new(&config, Duration::from_secs(1)).unwrap();
+
+	handler
+		.write("session-id", HashMap::from([("name", json!("Miguel"))]))
+		.await
+		.unwrap();
+
+	tokio::time::sleep(Duration::from_millis(1100)).await;
+
+	assert!(handler.read("session-id").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn redis_handler_reconnects_after_the_connection_drops() {
+	let server = StandInServer::spawn().await;
+	let config = RedisConfig {
+		url: server.url.clone(),
+		prefix: "sessions:".to_string(),
+	};
+	let mut handler = RedisHandler::new(&config, Duration::from_secs(60)).unwrap();
+
+	handler
+		.write("session-id", HashMap::from([("name", json!("Miguel"))]))
+		.await
+		.unwrap();
+
+	server.drop_connection.store(true, Ordering::SeqCst);
+	assert!(handler.read("session-id").await.is_err());
+
+	let attributes = handler.read("session-id").await.unwrap();
+	assert_eq!(attributes.get("name"), Some(&json!("Miguel")));
+}
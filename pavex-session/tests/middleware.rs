@@ -0,0 +1,174 @@
+use std::{
+	fs, future,
+	path::{Path, PathBuf},
+};
+
+use cookie::Cookie;
+use pavex::{
+	http::{header, HeaderMap, HeaderValue, Method, Version},
+	middleware::Next,
+	request::RequestHead,
+	response::Response,
+};
+use pavex_session::{FailureMode, Manager, SameSite, Session, SessionConfig, StartSession};
+use serde_json::json;
+
+mod common;
+
+fn session_dir(test: &str) -> PathBuf {
+	let path = std::env::temp_dir().join(format!("pavex-session-{}-{test}", std::process::id()));
+	let _ = fs::remove_dir_all(&path);
+
+	path
+}
+
+fn config(file_location: &Path) -> SessionConfig {
+	common::config(json!({
+		"driver": "file",
+		"file_location": file_location,
+		"secure": false,
+		"lottery": [0, 100],
+	}))
+}
+
+fn request(target: &str, session_id: Option<&str>) -> RequestHead {
+	let mut headers = HeaderMap::new();
+	if let Some(session_id) = session_id {
+		let cookie = format!("pavex_session={session_id}");
+		headers.insert(header::COOKIE, HeaderValue::from_str(&cookie).unwrap());
+	}
+
+	RequestHead {
+		headers,
+		method: Method::GET,
+		target: target.parse().unwrap(),
+		version: Version::HTTP_11,
+	}
+}
+
+async fn visit(manager: &Manager, config: &SessionConfig, req: &RequestHead) -> Response {
+	StartSession::handle(
+		req,
+		Session::new(),
+		config.clone(),
+		manager,
+		Next::new(future::ready(Response::ok())),
+	)
+	.await
+	.unwrap()
+}
+
+#[tokio::test]
+async fn unmodified_sessions_are_touched_instead_of_rewritten() {
+	let path = session_dir("unmodified");
+	let config = config(&path);
+	let manager = Manager::new(config.clone()).unwrap();
+
+	let response = visit(&manager, &config, &request("/dashboard", None)).await;
+	let cookie = response.headers().get(header::SET_COOKIE).unwrap();
+	let session_id = cookie.to_str().unwrap()["pavex_session=".len()..]
+		.split(';')
+		.next()
+		.unwrap()
+		.to_string();
+
+	// Reformat the stored session, so a rewrite can be told apart from a touch.
+	let session_file = path.join(&session_id);
+	let attributes: serde_json::Value =
+		serde_json::from_slice(&fs::read(&session_file).unwrap()).unwrap();
+	let reformatted = serde_json::to_vec_pretty(&attributes).unwrap();
+	fs::write(&session_file, &reformatted).unwrap();
+
+	visit(&manager, &config, &request("/dashboard", Some(&session_id))).await;
+	assert_eq!(
+		fs::read(&session_file).unwrap(),
+		reformatted,
+		"revisiting the same page doesn't rewrite the session"
+	);
+
+	visit(&manager, &config, &request("/profile", Some(&session_id))).await;
+	let attributes: serde_json::Value =
+		serde_json::from_slice(&fs::read(&session_file).unwrap()).unwrap();
+	assert_eq!(attributes["_previous.url"], json!("/profile"));
+	assert_ne!(fs::read(&session_file).unwrap(), reformatted);
+
+	fs::remove_dir_all(&path).unwrap();
+}
+
+#[tokio::test]
+async fn the_session_id_cookie_carries_the_configured_attributes() {
+	let path = session_dir("cookie-attributes");
+	let mut config = config(&path);
+	config.domain = Some("example.com".to_string());
+	config.same_site = SameSite::Strict;
+	config.secure = true;
+	let manager = Manager::new(config.clone()).unwrap();
+
+	let response = visit(&manager, &config, &request("/", None)).await;
+	let header = response.headers().get(header::SET_COOKIE).unwrap();
+	let cookie = Cookie::parse(header.to_str().unwrap()).unwrap();
+
+	assert_eq!(cookie.name(), "pavex_session");
+	assert_eq!(cookie.path(), Some("/"));
+	assert_eq!(cookie.domain(), Some("example.com"));
+	assert_eq!(cookie.secure(), Some(true));
+	assert_eq!(cookie.http_only(), Some(true));
+	assert_eq!(cookie.same_site(), Some(cookie::SameSite::Strict));
+	assert_eq!(
+		cookie.max_age(),
+		Some(cookie::time::Duration::seconds(2 * 60 * 60))
+	);
+
+	config.expire_on_close = true;
+	let response = visit(&manager, &config, &request("/", None)).await;
+	let header = response.headers().get(header::SET_COOKIE).unwrap();
+	assert_eq!(
+		Cookie::parse(header.to_str().unwrap()).unwrap().max_age(),
+		None
+	);
+
+	fs::remove_dir_all(&path).unwrap();
+}
+
+#[tokio::test]
+async fn malformed_session_cookies_start_a_fresh_session() {
+	let path = session_dir("malformed");
+	let config = config(&path);
+	let manager = Manager::new(config.clone()).unwrap();
+
+	for session_id in ["", "garbage", "..", "../../etc/passwd"] {
+		let response = visit(&manager, &config, &request("/", Some(session_id))).await;
+		let header = response.headers().get(header::SET_COOKIE).unwrap();
+		let cookie = Cookie::parse(header.to_str().unwrap()).unwrap();
+
+		assert_eq!(cookie.value().len(), 40, "{session_id:?} is replaced");
+		assert!(path.join(cookie.value()).exists());
+	}
+
+	fs::remove_dir_all(&path).unwrap();
+}
+
+#[tokio::test]
+async fn sessions_that_fail_to_read_are_replaced_in_open_mode() {
+	let path = session_dir("open-mode");
+	let mut config = config(&path);
+	config.failure_mode = FailureMode::Open;
+	let manager = Manager::new(config.clone()).unwrap();
+
+	// A directory where the session file should be makes reading it fail.
+	let session_id = "a".repeat(40);
+	fs::create_dir_all(path.join(&session_id).join("data")).unwrap();
+
+	let response = visit(&manager, &config, &request("/", Some(&session_id))).await;
+	let header = response.headers().get(header::SET_COOKIE).unwrap();
+	let cookie = Cookie::parse(header.to_str().unwrap()).unwrap();
+
+	assert_ne!(cookie.value(), session_id, "a new session is started");
+	assert!(path.join(cookie.value()).is_file());
+	assert!(
+		path.join(&session_id).join("data").is_dir(),
+		"the unreadable session is left alone"
+	);
+
+	fs::remove_dir_all(&path).unwrap();
+}
@@ -0,0 +1,273 @@
+use std::{collections::HashMap, time::Duration};
+
+use pavex_session::{
+	handlers::MemoryHandler, session::Error, ErrorBag, Handler, Session, SessionKey,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sessions_and_handlers_can_be_used_from_spawned_tasks() {
+	let mut session = Session::new();
+	session.start(None, HashMap::new()).unwrap();
+
+	let mut task_session = session.clone();
+	let mut handler = MemoryHandler::new(Duration::from_secs(60));
+	let mut stored = handler.clone();
+
+	let id = session.id();
+	tokio::spawn(async move {
+		task_session.set("name", "Miguel");
+
+		handler
+			.write(&task_session.id(), task_session.all())
+			.await
+			.unwrap();
+	})
+	.await
+	.unwrap();
+
+	assert_eq!(session.get::<String>("name").as_deref(), Some("Miguel"));
+
+	let stored = stored.read(&id).await.unwrap();
+	assert_eq!(stored.get("name"), Some(&serde_json::json!("Miguel")));
+}
+
+#[test]
+fn sessions_are_only_dirty_when_their_data_changes() {
+	let mut session = Session::new();
+	session.start(None, HashMap::new()).unwrap();
+	assert!(session.is_dirty(), "new sessions must be written");
+
+	let id = session.id();
+	let attributes = session.end().unwrap();
+
+	let mut session = Session::new();
+	session.start(Some(id), attributes).unwrap();
+	assert!(!session.is_dirty());
+
+	session.set("_token", session.token());
+	session.get::<String>("_token");
+	assert!(!session.is_dirty(), "reads and no-op writes must not dirty");
+
+	session.set("name", "Miguel");
+	assert!(session.is_dirty());
+}
+
+#[test]
+fn regenerating_a_session_rotates_its_id_and_token() {
+	let mut session = Session::new();
+	session.start(None, HashMap::new()).unwrap();
+	session.set("name", "Miguel");
+
+	let (id, token) = (session.id(), session.token());
+	session.regenerate(true);
+
+	assert_ne!(session.id(), id);
+	assert_ne!(session.token(), token);
+	assert_eq!(session.get::<String>("name").as_deref(), Some("Miguel"));
+
+	session.invalidate();
+	assert!(session.get::<String>("name").is_none());
+}
+
+#[test]
+fn masked_tokens_differ_per_call_and_verify_against_the_session_token() {
+	let mut session = Session::new();
+	session.start(None, HashMap::new()).unwrap();
+
+	let token = session.token().unwrap();
+	let masked = session.masked_token().unwrap();
+	assert_ne!(masked, token);
+	assert_ne!(masked, session.masked_token().unwrap());
+
+	assert!(session.verify_token(&token));
+	assert!(session.verify_token(&masked));
+	assert!(!session.verify_token("not-a-token"));
+	assert!(!session.verify_token(&masked[1..]));
+
+	session.regenerate_token();
+	assert!(!session.verify_token(&masked));
+}
+
+#[test]
+fn fallible_accessors_report_errors_instead_of_panicking() {
+	let mut session = Session::new();
+	assert!(matches!(
+		session.try_get::<String>("name"),
+		Err(Error::NotStarted)
+	));
+	assert!(session.all().is_empty());
+	assert!(matches!(session.try_all(), Err(Error::NotStarted)));
+	assert!(matches!(session.try_only("name"), Err(Error::NotStarted)));
+
+	session.start(None, HashMap::new()).unwrap();
+	session.set("name", "Miguel");
+	assert_eq!(
+		session.try_only("name").unwrap(),
+		HashMap::from([("name".to_string(), serde_json::json!("Miguel"))])
+	);
+
+	assert!(matches!(
+		session.try_get::<u64>("name"),
+		Err(Error::TypeMismatch { .. })
+	));
+	assert_eq!(session.get::<u64>("name"), None);
+	assert!(matches!(
+		session.try_push("name", 1),
+		Err(Error::TypeMismatch { .. })
+	));
+	assert!(matches!(
+		session.try_increment("name", 1),
+		Err(Error::TypeMismatch { .. })
+	));
+}
+
+#[test]
+fn typed_keys_read_and_write_their_own_type() {
+	const VISITS: SessionKey<u64> = SessionKey::new("visits");
+
+	let mut session = Session::new();
+	session.start(None, HashMap::new()).unwrap();
+	assert_eq!(session.get_typed(&VISITS), None);
+
+	session.set_typed(&VISITS, 3);
+	assert_eq!(session.get_typed(&VISITS), Some(3));
+	assert!(session.has(VISITS));
+}
+
+#[test]
+fn collection_helpers_create_and_update_values() {
+	let mut session = Session::new();
+	session.start(None, HashMap::new()).unwrap();
+
+	session.push("tags", "rust");
+	session.push("tags", "pavex");
+	session.push("tags", "rust");
+	assert_eq!(
+		session.get::<Vec<String>>("tags"),
+		Some(vec![
+			"rust".to_string(),
+			"pavex".to_string(),
+			"rust".to_string()
+		])
+	);
+
+	assert!(session.pull_from_array("tags", "rust"));
+	assert!(!session.pull_from_array("tags", "rust"));
+	assert_eq!(
+		session.get::<Vec<String>>("tags"),
+		Some(vec!["pavex".to_string()])
+	);
+
+	assert_eq!(session.increment("visits", 2), 2);
+	assert_eq!(session.decrement("visits", 1), 1);
+	assert!((session.increment_float("total", 1.5) - 1.5).abs() < f64::EPSILON);
+	assert!((session.increment_float("total", 0.25) - 1.75).abs() < f64::EPSILON);
+}
+
+#[test]
+fn dotted_keys_reach_into_nested_values() {
+	let mut session = Session::new();
+	session.start(None, HashMap::new()).unwrap();
+	session.set(
+		"cart",
+		serde_json::json!({ "items": [{ "sku": "A1" }, { "sku": "B2" }] }),
+	);
+	session.set("auth.user", 1);
+
+	assert_eq!(
+		session.get::<String>("cart.items.1.sku").as_deref(),
+		Some("B2")
+	);
+	assert_eq!(session.get::<u64>("auth.user"), Some(1));
+	assert!(session.has("cart.items.0"));
+	assert!(session.missing("cart.items.2"));
+}
+
+#[test]
+fn dotted_keys_write_and_forget_nested_values() {
+	let mut session = Session::new();
+	session.start(None, HashMap::new()).unwrap();
+	session.set(
+		"cart",
+		serde_json::json!({ "items": [{ "sku": "A1" }, { "sku": "B2" }] }),
+	);
+
+	session.set("cart.items.1.sku", "C3");
+	session.set("cart.coupon.code", "WELCOME");
+	assert_eq!(
+		session.get::<String>("cart.items.1.sku").as_deref(),
+		Some("C3")
+	);
+	assert_eq!(
+		session.get::<String>("cart.coupon.code").as_deref(),
+		Some("WELCOME")
+	);
+	assert!(!session.exists("cart.items.1.sku.extra"));
+
+	session.forget(vec![
+		"cart.items.0".to_string(),
+		"cart.coupon.code".to_string(),
+	]);
+	assert_eq!(
+		session.get::<String>("cart.items.0.sku").as_deref(),
+		Some("C3")
+	);
+	assert!(session.missing("cart.coupon.code"));
+	assert!(session.exists("cart.coupon"));
+
+	assert!(matches!(
+		session.try_set("cart.items.0.sku.name", "D4"),
+		Err(Error::TypeMismatch { .. })
+	));
+
+	// Without an attribute to write into, dotted keys are stored as-is.
+	session.set("auth.user", 1);
+	assert_eq!(session.all().get("auth.user"), Some(&serde_json::json!(1)));
+	session.forget("auth.user");
+	assert!(session.missing("auth.user"));
+}
+
+#[test]
+fn dotted_keys_write_to_an_attribute_with_that_exact_name_first() {
+	let mut session = Session::new();
+	session.start(None, HashMap::new()).unwrap();
+	session.replace(HashMap::from([
+		("cart".to_string(), serde_json::json!({ "total": 10 })),
+		("cart.total".to_string(), serde_json::json!(20)),
+	]));
+
+	session.set("cart.total", 30);
+	assert_eq!(session.get::<u64>("cart.total"), Some(30));
+	assert_eq!(
+		session.all().get("cart"),
+		Some(&serde_json::json!({ "total": 10 }))
+	);
+}
+
+#[test]
+fn input_and_errors_are_flashed_to_the_next_request() {
+	let mut session = Session::new();
+	session.start(None, HashMap::new()).unwrap();
+
+	session.with_input(serde_json::json!({
+		"email": "miguel@example.com",
+		"password": "secret",
+		"password_confirmation": "secret",
+	}));
+	session.with_errors(ErrorBag::new().with("email", "Invalid email or password"));
+
+	let id = session.id();
+	let attributes = session.end().unwrap();
+	let mut session = Session::new();
+	session.start(Some(id), attributes).unwrap();
+
+	assert_eq!(
+		session.old::<String>("email").as_deref(),
+		Some("miguel@example.com")
+	);
+	assert_eq!(session.old::<String>("password"), None);
+	assert_eq!(
+		session.errors().first("email"),
+		Some("Invalid email or password")
+	);
+}
@@ -0,0 +1,62 @@
+use std::{collections::HashMap, time::Duration};
+
+use pavex_session::{handlers::Backend, Handler, Manager, SessionConfig, Sweeper};
+use serde_json::json;
+
+mod common;
+
+/// A config whose sessions expire as soon as they're written, so sweeping never has to wait for them.
+fn config(sweep_interval: Option<&str>) -> SessionConfig {
+	common::config(json!({
+		"lifetime": "0s",
+		"sweep_interval": sweep_interval,
+	}))
+}
+
+async fn write(backend: &mut Backend, id: &str) {
+	backend
+		.write(id, HashMap::<String, serde_json::Value>::new())
+		.await
+		.unwrap();
+}
+
+/// Move the paused clock to the next sweep, then let the woken sweeper run before checking on it.
+async fn next_sweep() {
+	tokio::time::sleep(Duration::from_secs(60)).await;
+	tokio::task::yield_now().await;
+}
+
+#[tokio::test]
+async fn no_sweeper_is_created_without_an_interval() {
+	let config = config(None);
+	let manager = Manager::new(config.clone()).unwrap();
+
+	assert!(Sweeper::new(manager.backend(), &config).is_none());
+}
+
+#[tokio::test]
+async fn sweeping_purges_expired_sessions() {
+	let config = config(Some("1min"));
+	let mut backend = Backend::from_config(&config).unwrap();
+	let mut sweeper = Sweeper::new(backend.clone(), &config).unwrap();
+
+	write(&mut backend, "expired").await;
+
+	assert_eq!(sweeper.sweep().await.unwrap(), 1);
+	assert_eq!(sweeper.sweep().await.unwrap(), 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn the_manager_sweeps_its_own_sessions_until_dropped() {
+	let manager = Manager::new(config(Some("1min"))).unwrap();
+	let mut backend = manager.backend();
+
+	write(&mut backend, "expired").await;
+	next_sweep().await;
+	assert_eq!(backend.collect_garbage(&Duration::ZERO).await.unwrap(), 0);
+
+	drop(manager);
+	write(&mut backend, "expired-after-shutdown").await;
+	next_sweep().await;
+	assert_eq!(backend.collect_garbage(&Duration::ZERO).await.unwrap(), 1);
+}
@@ -15,7 +15,7 @@ pub mod telemetry;
 /// Errors if the application state can't be built.
 pub async fn run(config: Config) -> anyhow::Result<()> {
 	ensemble::setup(&config.database.url)?;
-	ensemble::migrate!(migrations::CreateUsersTable).await?;
+	migrate().await?;
 
 	let tcp_listener = config
 		.server
@@ -35,3 +35,19 @@ pub async fn run(config: Config) -> anyhow::Result<()> {
 
 	Ok(())
 }
+
+/// Run the database migrations that haven't been run yet.
+///
+/// # Errors
+///
+/// Errors if a migration fails.
+pub async fn migrate() -> anyhow::Result<()> {
+	ensemble::migrate!(
+		migrations::CreateUsersTable,
+		migrations::CreateSessionsTable,
+		migrations::CreatePersonalAccessTokensTable
+	)
+	.await?;
+
+	Ok(())
+}
@@ -20,3 +20,46 @@ impl Migration for CreateUsersTable {
 		Schema::drop("users").await
 	}
 }
+
+#[derive(Debug, Default)]
+pub struct CreateSessionsTable;
+
+#[ensemble::async_trait]
+impl Migration for CreateSessionsTable {
+	async fn up(&self) -> Result<(), Error> {
+		Schema::create("sessions", |table| {
+			table.string("id").primary(true);
+			table.integer("user_id").nullable(true).index(true);
+			table.string("ip_address").nullable(true);
+			table.text("user_agent").nullable(true);
+			table.text("payload");
+			table.integer("last_activity").index(true);
+		})
+		.await
+	}
+
+	async fn down(&self) -> Result<(), Error> {
+		Schema::drop("sessions").await
+	}
+}
+
+#[derive(Debug, Default)]
+pub struct CreatePersonalAccessTokensTable;
+
+#[ensemble::async_trait]
+impl Migration for CreatePersonalAccessTokensTable {
+	async fn up(&self) -> Result<(), Error> {
+		Schema::create("personal_access_tokens", |table| {
+			table.id();
+			table.integer("user_id").index(true);
+			table.string("name");
+			table.string("token").unique(true);
+			table.timestamps();
+		})
+		.await
+	}
+
+	async fn down(&self) -> Result<(), Error> {
+		Schema::drop("personal_access_tokens").await
+	}
+}
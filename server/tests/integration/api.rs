@@ -0,0 +1,20 @@
+use crate::helpers::TestApi;
+use pavex::http::StatusCode;
+
+#[tokio::test]
+async fn api_routes_require_a_bearer_token() {
+    let api = TestApi::spawn().await;
+
+    for authorization in [None, Some("Basic dXNlcjpwYXNz")] {
+        let mut request = api.client.get(&format!("{}/api/user", &api.address));
+        if let Some(authorization) = authorization {
+            request = request.header("Authorization", authorization);
+        }
+
+        let response = request.send().await.expect("Failed to execute request.");
+        assert_eq!(
+            response.status().as_u16(),
+            StatusCode::UNAUTHORIZED.as_u16()
+        );
+    }
+}
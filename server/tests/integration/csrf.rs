@@ -0,0 +1,77 @@
+use crate::helpers::TestApi;
+use pavex::http::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn state_changing_requests_require_a_csrf_token() {
+    let api = TestApi::spawn().await;
+    let credentials = json!({ "email": "nobody@example.com", "password": "secret" });
+
+    let response = api
+        .client
+        .post(&format!("{}/auth/login", &api.address))
+        .json(&credentials)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 419);
+
+    let token = api.csrf_token().await;
+    let response = api
+        .client
+        .post(&format!("{}/auth/login", &api.address))
+        .header("X-CSRF-TOKEN", token)
+        .json(&credentials)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), StatusCode::OK.as_u16());
+}
+
+#[tokio::test]
+async fn cross_site_requests_are_rejected() {
+    let api = TestApi::spawn().await;
+    let token = api.csrf_token().await;
+
+    let response = api
+        .client
+        .post(&format!("{}/auth/login", &api.address))
+        .header("X-CSRF-TOKEN", token)
+        .header("Origin", "https://evil.example.com")
+        .json(&json!({ "email": "nobody@example.com", "password": "secret" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), StatusCode::FORBIDDEN.as_u16());
+}
+
+#[tokio::test]
+async fn only_the_body_of_state_changing_requests_is_read() {
+    let api = TestApi::spawn().await;
+    let token = api.csrf_token().await;
+    // Larger than the default body size limit of 2MB.
+    let oversized = "a".repeat(3 * 1024 * 1024);
+
+    let response = api
+        .client
+        .get(&format!("{}/auth/login", &api.address))
+        .body(oversized.clone())
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), StatusCode::OK.as_u16());
+
+    let response = api
+        .client
+        .post(&format!("{}/auth/login", &api.address))
+        .header("X-CSRF-TOKEN", token)
+        .body(oversized)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(
+        response.status().as_u16(),
+        StatusCode::PAYLOAD_TOO_LARGE.as_u16()
+    );
+}
@@ -1,10 +1,14 @@
+use std::sync::Arc;
+
 use app::config::Config;
 use pavex::server::Server;
+use reqwest::cookie::{CookieStore, Jar};
 use server::config::{self, Environment};
 
 pub struct TestApi {
 	pub address: String,
 	pub client: reqwest::Client,
+	pub cookies: Arc<Jar>,
 }
 
 impl TestApi {
@@ -29,9 +33,16 @@ impl TestApi {
 
 		tokio::spawn(async move { sdk::run(server_builder, application_state).await });
 
+		let cookies = Arc::new(Jar::default());
+		let client = reqwest::Client::builder()
+			.cookie_provider(cookies.clone())
+			.build()
+			.unwrap();
+
 		TestApi {
 			address,
-			client: reqwest::Client::new(),
+			client,
+			cookies,
 		}
 	}
 
@@ -49,4 +60,28 @@ impl TestApi {
 			.await
 			.expect("Failed to execute request.")
 	}
+
+	/// Start a session for this client and return its CSRF token.
+	/// Send it back as the `X-CSRF-TOKEN` header on state-changing requests.
+	pub async fn csrf_token(&self) -> String {
+		self.client
+			.get(&format!("{}/auth/login", &self.address))
+			.send()
+			.await
+			.expect("Failed to execute request.");
+
+		let url = self.address.parse().unwrap();
+		let cookies = self
+			.cookies
+			.cookies(&url)
+			.expect("The server didn't set any cookies");
+
+		cookies
+			.to_str()
+			.unwrap()
+			.split("; ")
+			.find_map(|cookie| cookie.strip_prefix("XSRF-TOKEN="))
+			.expect("The server didn't issue a CSRF token")
+			.to_string()
+	}
 }
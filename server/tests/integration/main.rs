@@ -1,2 +1,5 @@
+mod api;
+mod csrf;
 mod ping;
+mod sessions;
 mod helpers;
\ No newline at end of file
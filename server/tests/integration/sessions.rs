@@ -0,0 +1,125 @@
+use std::{collections::HashMap, time::Duration};
+
+use ensemble::Model;
+use pavex_session::{
+    handlers::{
+        database::{StoredSession, USER_KEY},
+        DatabaseHandler,
+    },
+    Handler,
+};
+use serde_json::json;
+use tokio::sync::OnceCell;
+
+use crate::helpers::TestApi;
+
+/// Connect to the test database, migrating it once for the whole test run.
+async fn handler() -> DatabaseHandler {
+    static MIGRATED: OnceCell<()> = OnceCell::const_new();
+
+    TestApi::spawn().await;
+    MIGRATED
+        .get_or_init(|| async { server::migrate().await.unwrap() })
+        .await;
+
+    DatabaseHandler::new(Duration::from_secs(60), vec![])
+}
+
+/// An ID no other test run uses, so tests sharing the database don't see each other's sessions.
+fn session_id(test: &str) -> String {
+    format!("{test}{}", std::process::id())
+}
+
+#[tokio::test]
+async fn database_sessions_are_persisted_and_read_back() {
+    let mut handler = handler().await;
+    let id = session_id("persisted");
+
+    handler
+        .write(&id, HashMap::from([("name", json!("Miguel"))]))
+        .await
+        .unwrap();
+    handler
+        .write(&id, HashMap::from([("name", json!("Luca"))]))
+        .await
+        .unwrap();
+    assert_eq!(handler.read(&id).await.unwrap()["name"], json!("Luca"));
+
+    handler.destroy(&id).await.unwrap();
+    assert!(handler.read(&id).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn concurrent_first_writes_of_a_session_both_succeed() {
+    let mut first = handler().await;
+    let mut second = first.clone();
+    let id = session_id("concurrent");
+
+    let (created, updated) = tokio::join!(
+        first.write(&id, HashMap::from([("tab", json!(1))])),
+        second.write(&id, HashMap::from([("tab", json!(2))])),
+    );
+    created.unwrap();
+    updated.unwrap();
+
+    first.destroy(&id).await.unwrap();
+}
+
+#[tokio::test]
+async fn expired_database_sessions_are_discarded() {
+    let mut handler = handler().await;
+    let id = session_id("expired");
+
+    for id in [id.clone(), format!("{id}collected")] {
+        StoredSession::create(StoredSession {
+            id,
+            user_id: None,
+            ip_address: None,
+            user_agent: None,
+            payload: json!({ "name": "Miguel" }).to_string(),
+            last_activity: 0,
+        })
+        .await
+        .unwrap();
+    }
+
+    assert!(handler.read(&id).await.unwrap().is_empty());
+    let collected = handler
+        .collect_garbage(&Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert!(collected >= 1, "other runs may leave expired sessions too");
+    assert!(handler
+        .read(&format!("{id}collected"))
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn the_sessions_of_a_user_can_be_listed_and_revoked() {
+    let mut handler = handler().await;
+    let user_id = 1_000_000_000 + u64::from(std::process::id());
+
+    for id in [session_id("laptop"), session_id("phone")] {
+        handler
+            .write(&id, HashMap::from([(USER_KEY, json!(user_id))]))
+            .await
+            .unwrap();
+    }
+
+    let mut sessions = DatabaseHandler::sessions_for(user_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|session| session.id)
+        .collect::<Vec<_>>();
+    sessions.sort();
+    assert_eq!(sessions, [session_id("laptop"), session_id("phone")]);
+
+    assert_eq!(DatabaseHandler::revoke_all(user_id).await.unwrap(), 2);
+    assert!(DatabaseHandler::sessions_for(user_id)
+        .await
+        .unwrap()
+        .is_empty());
+}